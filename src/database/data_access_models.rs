@@ -3,7 +3,7 @@ use std::fmt::Display;
 use rusqlite::Connection;
 use tracing::error;
 
-use super::schema::{EditorContent, Request};
+use super::schema::{DiffSettings, EditorContent, EditorHistoryEntry, Request};
 
 #[derive(Debug)]
 pub enum DaoError {
@@ -22,7 +22,7 @@ impl Display for DaoError {
 
 impl std::error::Error for DaoError {}
 
-pub struct RequestDao<'a> {
+struct RequestDao<'a> {
     conn: &'a Connection,
 }
 
@@ -58,16 +58,16 @@ pub struct EditorContentDao<'a> {
 }
 
 impl<'a> EditorContentDao<'a> {
-    fn new(conn: &Connection) -> EditorContentDao {
+    pub fn new(conn: &Connection) -> EditorContentDao<'_> {
         EditorContentDao { conn }
     }
 
-    fn get_editor_content(
+    pub fn get_editor_content(
         &self,
         project_id: i32,
     ) -> Result<EditorContent, Box<dyn std::error::Error>> {
         let mut statement = self.conn.prepare(
-            "SELECT id, project_id, content from jdiff_editor_content where project_id = ?1",
+            "SELECT id, project_id, content, position, history_key from jdiff_editor_content where project_id = ?1",
         )?;
 
         let result = statement
@@ -76,6 +76,8 @@ impl<'a> EditorContentDao<'a> {
                     id: row.get(0)?,
                     project_id: row.get(1)?,
                     content: row.get(2)?,
+                    position: row.get(3)?,
+                    history_key: row.get(4)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -95,4 +97,295 @@ impl<'a> EditorContentDao<'a> {
 
         Ok(result[0].clone())
     }
+
+    // one row per editor, in the order the editors are shown
+    pub fn get_editor_contents(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<EditorContent>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, project_id, content, position, history_key from jdiff_editor_content where project_id = ?1 ORDER BY position, id",
+        )?;
+
+        let result = statement
+            .query_map([project_id], |row| {
+                Ok(EditorContent {
+                    id: row.get(0)?,
+                    project_id: row.get(1)?,
+                    content: row.get(2)?,
+                    position: row.get(3)?,
+                    history_key: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(result)
+    }
+
+    pub fn save_editor_content(
+        &self,
+        editor_content: &EditorContent,
+    ) -> Result<i32, Box<dyn std::error::Error>> {
+        self.conn.execute(
+            "INSERT INTO jdiff_editor_content (project_id, content, position, history_key)
+            VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                editor_content.project_id,
+                editor_content.content,
+                editor_content.position,
+                editor_content.history_key
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid() as i32)
+    }
+
+    pub fn update_editor_content(
+        &self,
+        editor_content: &EditorContent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn.execute(
+            "UPDATE jdiff_editor_content SET content = ?2, position = ?3 WHERE id = ?1",
+            rusqlite::params![
+                editor_content.id,
+                editor_content.content,
+                editor_content.position
+            ],
+        )?;
+        Ok(())
+    }
+
+    // the history goes together with the content it belongs to
+    pub fn delete_editor_content(&self, id: i32) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn.execute(
+            "DELETE FROM jdiff_editor_history WHERE editor_content_id = ?1",
+            [id],
+        )?;
+        self.conn
+            .execute("DELETE FROM jdiff_editor_content WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn get_editor_history(
+        &self,
+        editor_content_id: i32,
+    ) -> Result<Vec<EditorHistoryEntry>, Box<dyn std::error::Error>> {
+        self.query_editor_history(editor_content_id, "operations")
+    }
+
+    // operations are left empty, saving only has to know which steps are there
+    pub fn get_editor_history_steps(
+        &self,
+        editor_content_id: i32,
+    ) -> Result<Vec<EditorHistoryEntry>, Box<dyn std::error::Error>> {
+        self.query_editor_history(editor_content_id, "''")
+    }
+
+    fn query_editor_history(
+        &self,
+        editor_content_id: i32,
+        operations_column: &str,
+    ) -> Result<Vec<EditorHistoryEntry>, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(&format!(
+            "SELECT id, editor_content_id, node_id, parent_id, redo_child_id, is_current, {},
+                operation_count, cursor_before_column, cursor_before_line, cursor_after_column,
+                cursor_after_line
+            from jdiff_editor_history where editor_content_id = ?1",
+            operations_column
+        ))?;
+
+        let result = statement
+            .query_map([editor_content_id], |row| {
+                Ok(EditorHistoryEntry {
+                    id: row.get(0)?,
+                    editor_content_id: row.get(1)?,
+                    node_id: row.get(2)?,
+                    parent_id: row.get(3)?,
+                    redo_child_id: row.get(4)?,
+                    is_current: row.get(5)?,
+                    operations: row.get(6)?,
+                    operation_count: row.get(7)?,
+                    cursor_before_column: row.get(8)?,
+                    cursor_before_line: row.get(9)?,
+                    cursor_after_column: row.get(10)?,
+                    cursor_after_line: row.get(11)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(result)
+    }
+
+    pub fn save_editor_history(
+        &self,
+        entries: &[EditorHistoryEntry],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "INSERT INTO jdiff_editor_history (editor_content_id, node_id, parent_id, redo_child_id, is_current,
+                operations, operation_count, cursor_before_column, cursor_before_line, cursor_after_column,
+                cursor_after_line)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+
+        for entry in entries {
+            statement.execute(rusqlite::params![
+                entry.editor_content_id,
+                entry.node_id,
+                entry.parent_id,
+                entry.redo_child_id,
+                entry.is_current,
+                entry.operations,
+                entry.operation_count,
+                entry.cursor_before_column,
+                entry.cursor_before_line,
+                entry.cursor_after_column,
+                entry.cursor_after_line,
+            ])?;
+        }
+        Ok(())
+    }
+
+    // everything but the operations, those stay as they are
+    pub fn update_editor_history_steps(
+        &self,
+        entries: &[EditorHistoryEntry],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "UPDATE jdiff_editor_history SET parent_id = ?2, redo_child_id = ?3, is_current = ?4,
+                cursor_before_column = ?5, cursor_before_line = ?6, cursor_after_column = ?7,
+                cursor_after_line = ?8
+            WHERE id = ?1",
+        )?;
+
+        for entry in entries {
+            statement.execute(rusqlite::params![
+                entry.id,
+                entry.parent_id,
+                entry.redo_child_id,
+                entry.is_current,
+                entry.cursor_before_column,
+                entry.cursor_before_line,
+                entry.cursor_after_column,
+                entry.cursor_after_line,
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_editor_history_steps(
+        &self,
+        ids: &[i32],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut statement = self
+            .conn
+            .prepare("DELETE FROM jdiff_editor_history WHERE id = ?1")?;
+
+        for id in ids {
+            statement.execute([id])?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ProjectDao<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> ProjectDao<'a> {
+    pub fn new(conn: &Connection) -> ProjectDao<'_> {
+        ProjectDao { conn }
+    }
+
+    pub fn get_or_create_project(&self, name: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id from jdiff_projects where name = ?1")?;
+        let ids = statement
+            .query_map([name], |row| row.get(0))?
+            .collect::<Result<Vec<i32>, _>>()?;
+
+        if let Some(id) = ids.first() {
+            return Ok(*id);
+        }
+
+        self.conn
+            .execute("INSERT INTO jdiff_projects (name) VALUES (?1)", [name])?;
+        Ok(self.conn.last_insert_rowid() as i32)
+    }
+}
+
+pub struct DiffSettingsDao<'a> {
+    conn: &'a Connection,
+}
+
+impl<'a> DiffSettingsDao<'a> {
+    pub fn new(conn: &Connection) -> DiffSettingsDao<'_> {
+        DiffSettingsDao { conn }
+    }
+
+    pub fn get_diff_settings(
+        &self,
+        project_id: i32,
+    ) -> Result<DiffSettings, Box<dyn std::error::Error>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, project_id, ignore_key_order, array_mode, array_key, ignore_whitespace, ignored_paths,
+                compare_numbers_by_value, absolute_epsilon, relative_epsilon, coerce_types
+            from jdiff_diff_options where project_id = ?1",
+        )?;
+
+        let result = statement
+            .query_map([project_id], |row| {
+                Ok(DiffSettings {
+                    id: row.get(0)?,
+                    project_id: row.get(1)?,
+                    ignore_key_order: row.get(2)?,
+                    array_mode: row.get(3)?,
+                    array_key: row.get(4)?,
+                    ignore_whitespace: row.get(5)?,
+                    ignored_paths: row.get(6)?,
+                    compare_numbers_by_value: row.get(7)?,
+                    absolute_epsilon: row.get(8)?,
+                    relative_epsilon: row.get(9)?,
+                    coerce_types: row.get(10)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // project_id is unique, so there's at most one row
+        match result.first() {
+            Some(diff_settings) => Ok(diff_settings.clone()),
+            None => Err(Box::new(DaoError::QueryReturnedNoRows)),
+        }
+    }
+
+    pub fn save_diff_settings(
+        &self,
+        diff_settings: &DiffSettings,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.conn.execute(
+            "INSERT INTO jdiff_diff_options (project_id, ignore_key_order, array_mode, array_key, ignore_whitespace, ignored_paths,
+                compare_numbers_by_value, absolute_epsilon, relative_epsilon, coerce_types)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT(project_id) DO UPDATE SET
+                ignore_key_order = excluded.ignore_key_order,
+                array_mode = excluded.array_mode,
+                array_key = excluded.array_key,
+                ignore_whitespace = excluded.ignore_whitespace,
+                ignored_paths = excluded.ignored_paths,
+                compare_numbers_by_value = excluded.compare_numbers_by_value,
+                absolute_epsilon = excluded.absolute_epsilon,
+                relative_epsilon = excluded.relative_epsilon,
+                coerce_types = excluded.coerce_types",
+            rusqlite::params![
+                diff_settings.project_id,
+                diff_settings.ignore_key_order,
+                diff_settings.array_mode,
+                diff_settings.array_key,
+                diff_settings.ignore_whitespace,
+                diff_settings.ignored_paths,
+                diff_settings.compare_numbers_by_value,
+                diff_settings.absolute_epsilon,
+                diff_settings.relative_epsilon,
+                diff_settings.coerce_types,
+            ],
+        )?;
+        Ok(())
+    }
 }
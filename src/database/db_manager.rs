@@ -1,7 +1,43 @@
+use std::collections::HashMap;
+
+use ropey::Rope;
 use rusqlite::Connection;
-use tracing::info;
+use tracing::{error, info, warn};
+
+use crate::{
+    diff::options::{parse_ignored_path, ArrayMode, DiffOptions},
+    json::{
+        format::escape_string,
+        parser::parse,
+        value::{JsonNode, JsonValue},
+    },
+    models::{
+        app_state::AppStateActions,
+        editor::{
+            editor_container_models::EditorContainerModelActions,
+            editor_model::EditorModel,
+            editor_traits::History,
+            history_model::{EditOperation, HistoryModel, HistoryNode},
+        },
+    },
+};
+
+use super::{
+    data_access_models::{DaoError, DiffSettingsDao, EditorContentDao, ProjectDao},
+    migrations::get_migrations,
+    schema::{DiffSettings, EditorContent, EditorHistoryEntry},
+};
+
+// there's no way to pick a project yet, so everything goes to this one
+const DEFAULT_PROJECT_NAME: &str = "default";
 
-use super::migrations::get_migrations;
+#[derive(Debug, Clone)]
+pub enum DatabaseActions {
+    LoadDiffOptions,
+    SaveDiffOptions(DiffOptions),
+    LoadEditors,
+    SaveEditors(Vec<EditorModel>),
+}
 
 pub struct DBManager {
     connection: Connection,
@@ -29,7 +65,7 @@ impl DBManager {
         self.connection.close().unwrap();
     }
 
-    pub fn migrate_schema(self) {
+    pub fn migrate_schema(&self) {
         info!("Beginning applying migrations");
         let migrations = get_migrations();
         info!("migrations count {:?}", migrations.len());
@@ -40,4 +76,360 @@ impl DBManager {
         }
         info!("Migrations applied succesfully!");
     }
+
+    pub fn handle_action(&self, action: DatabaseActions) -> Option<AppStateActions> {
+        let project_id =
+            match ProjectDao::new(&self.connection).get_or_create_project(DEFAULT_PROJECT_NAME) {
+                Ok(project_id) => project_id,
+                Err(err) => {
+                    error!("Cannot find the default project: {}", err);
+                    return None;
+                }
+            };
+
+        match action {
+            DatabaseActions::LoadDiffOptions => {
+                match DiffSettingsDao::new(&self.connection).get_diff_settings(project_id) {
+                    Ok(diff_settings) => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::LoadDiffOptions(to_diff_options(
+                            &diff_settings,
+                        )),
+                    )),
+                    Err(err) => {
+                        // nothing saved yet, the defaults are fine
+                        if !matches!(
+                            err.downcast_ref::<DaoError>(),
+                            Some(DaoError::QueryReturnedNoRows)
+                        ) {
+                            error!("Cannot load diff options: {}", err);
+                        }
+                        None
+                    }
+                }
+            }
+            DatabaseActions::SaveDiffOptions(options) => {
+                let diff_settings = to_diff_settings(project_id, &options);
+                if let Err(err) =
+                    DiffSettingsDao::new(&self.connection).save_diff_settings(&diff_settings)
+                {
+                    error!("Cannot save diff options: {}", err);
+                }
+                None
+            }
+            DatabaseActions::LoadEditors => match self.load_editors(project_id) {
+                Ok(editors) if editors.is_empty() => None,
+                Ok(editors) => Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::LoadEditors(editors),
+                )),
+                Err(err) => {
+                    error!("Cannot load the editors: {}", err);
+                    None
+                }
+            },
+            DatabaseActions::SaveEditors(editors) => {
+                if let Err(err) = self.save_editors(project_id, &editors) {
+                    error!("Cannot save the editors: {}", err);
+                }
+                None
+            }
+        }
+    }
+
+    fn load_editors(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<EditorModel>, Box<dyn std::error::Error>> {
+        let dao = EditorContentDao::new(&self.connection);
+        let mut editors = Vec::new();
+
+        for editor_content in dao.get_editor_contents(project_id)? {
+            let entries = dao.get_editor_history(editor_content.id)?;
+            // the text is what matters, without a history it's still worth opening
+            let mut history =
+                to_history_model(&entries, &editor_content.content).unwrap_or_else(|| {
+                    warn!(
+                        "Cannot restore the history of editor content {}, starting a new one",
+                        editor_content.id
+                    );
+                    HistoryModel::default()
+                });
+
+            if !editor_content.history_key.is_empty() {
+                history.set_key(editor_content.history_key.clone());
+            }
+
+            let mut editor = EditorModel::default();
+            editor.restore_history(&editor_content.content, history);
+            editors.push(editor);
+        }
+
+        Ok(editors)
+    }
+
+    // rows are matched to editors by their history key, so only what changed since the
+    // last save gets written. It's either all saved or nothing is
+    fn save_editors(
+        &self,
+        project_id: i32,
+        editors: &[EditorModel],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let transaction = self.connection.unchecked_transaction()?;
+        let dao = EditorContentDao::new(&self.connection);
+        let mut saved_contents = dao.get_editor_contents(project_id)?;
+
+        for (position, editor) in editors.iter().enumerate() {
+            let history = editor.get_history();
+            let mut editor_content = EditorContent {
+                id: 0,
+                project_id,
+                content: editor.get_content(),
+                position: position as i64,
+                history_key: history.get_key().to_string(),
+            };
+
+            let saved_index = saved_contents.iter().position(|saved_content| {
+                !saved_content.history_key.is_empty()
+                    && saved_content.history_key == editor_content.history_key
+            });
+            let Some(saved_index) = saved_index else {
+                let editor_content_id = dao.save_editor_content(&editor_content)?;
+                dao.save_editor_history(&to_history_entries(editor_content_id, history))?;
+                continue;
+            };
+
+            let saved_content = saved_contents.swap_remove(saved_index);
+            editor_content.id = saved_content.id;
+            if saved_content != editor_content {
+                dao.update_editor_content(&editor_content)?;
+            }
+            save_history_changes(&dao, editor_content.id, history)?;
+        }
+
+        // closed editors
+        for saved_content in saved_contents {
+            dao.delete_editor_content(saved_content.id)?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+// steps keep their node id for as long as they're in the history, so they're compared by it
+fn save_history_changes(
+    dao: &EditorContentDao,
+    editor_content_id: i32,
+    history: &HistoryModel,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut saved_entries: HashMap<i64, EditorHistoryEntry> = dao
+        .get_editor_history_steps(editor_content_id)?
+        .into_iter()
+        .map(|entry| (entry.node_id, entry))
+        .collect();
+    let mut added = Vec::new();
+    let mut updated = Vec::new();
+    let mut removed = Vec::new();
+
+    for (node_id, node) in history.get_nodes() {
+        let mut entry = to_history_entry(editor_content_id, history, *node_id, node);
+        let Some(saved_entry) = saved_entries.remove(&entry.node_id) else {
+            entry.operations = encode_operations(&node.operations);
+            added.push(entry);
+            continue;
+        };
+
+        entry.id = saved_entry.id;
+        if entry.operation_count != saved_entry.operation_count {
+            removed.push(saved_entry.id);
+            entry.operations = encode_operations(&node.operations);
+            added.push(entry);
+        } else if entry != saved_entry {
+            updated.push(entry);
+        }
+    }
+    // trimmed away
+    removed.extend(saved_entries.values().map(|entry| entry.id));
+
+    dao.delete_editor_history_steps(&removed)?;
+    dao.update_editor_history_steps(&updated)?;
+    dao.save_editor_history(&added)?;
+    Ok(())
+}
+
+fn to_diff_settings(project_id: i32, options: &DiffOptions) -> DiffSettings {
+    let (array_mode, array_key) = match &options.array_mode {
+        ArrayMode::Ordered => ("ordered", ""),
+        ArrayMode::Unordered => ("unordered", ""),
+        ArrayMode::MatchByKey(key) => ("key", key.as_str()),
+    };
+
+    DiffSettings {
+        id: 0,
+        project_id,
+        ignore_key_order: options.ignore_key_order,
+        array_mode: array_mode.into(),
+        array_key: array_key.into(),
+        ignore_whitespace: options.ignore_whitespace,
+        ignored_paths: options
+            .ignored_paths
+            .iter()
+            .map(|ignored_path| ignored_path.expression.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        compare_numbers_by_value: options.compare_numbers_by_value,
+        absolute_epsilon: options.absolute_epsilon,
+        relative_epsilon: options.relative_epsilon,
+        coerce_types: options.coerce_types,
+    }
+}
+
+fn to_diff_options(diff_settings: &DiffSettings) -> DiffOptions {
+    let array_mode = match diff_settings.array_mode.as_str() {
+        "unordered" => ArrayMode::Unordered,
+        "key" => ArrayMode::MatchByKey(diff_settings.array_key.clone()),
+        _ => ArrayMode::Ordered,
+    };
+
+    let ignored_paths = diff_settings
+        .ignored_paths
+        .lines()
+        .filter(|expression| !expression.is_empty())
+        .filter_map(|expression| match parse_ignored_path(expression) {
+            Ok(ignored_path) => Some(ignored_path),
+            Err(err) => {
+                warn!("Skipping saved ignored path: {}", err);
+                None
+            }
+        })
+        .collect();
+
+    DiffOptions {
+        ignore_key_order: diff_settings.ignore_key_order,
+        array_mode,
+        ignore_whitespace: diff_settings.ignore_whitespace,
+        ignored_paths,
+        compare_numbers_by_value: diff_settings.compare_numbers_by_value,
+        absolute_epsilon: diff_settings.absolute_epsilon,
+        relative_epsilon: diff_settings.relative_epsilon,
+        coerce_types: diff_settings.coerce_types,
+    }
+}
+
+fn to_history_entries(editor_content_id: i32, history: &HistoryModel) -> Vec<EditorHistoryEntry> {
+    history
+        .get_nodes()
+        .iter()
+        .map(|(node_id, node)| EditorHistoryEntry {
+            operations: encode_operations(&node.operations),
+            ..to_history_entry(editor_content_id, history, *node_id, node)
+        })
+        .collect()
+}
+
+// without the operations, they're the bulk of a step and only encoded when they get written
+fn to_history_entry(
+    editor_content_id: i32,
+    history: &HistoryModel,
+    node_id: usize,
+    node: &HistoryNode,
+) -> EditorHistoryEntry {
+    EditorHistoryEntry {
+        id: 0,
+        editor_content_id,
+        node_id: node_id as i64,
+        parent_id: node.parent.map(|parent| parent as i64),
+        redo_child_id: node.redo_child.map(|child| child as i64),
+        is_current: node_id == history.get_current(),
+        operations: String::new(),
+        operation_count: node.operations.len() as i64,
+        cursor_before_column: node.cursor_before.0 as i64,
+        cursor_before_line: node.cursor_before.1 as i64,
+        cursor_after_column: node.cursor_after.0 as i64,
+        cursor_after_line: node.cursor_after.1 as i64,
+    }
+}
+
+fn to_history_model(entries: &[EditorHistoryEntry], content: &str) -> Option<HistoryModel> {
+    let current = entries.iter().find(|entry| entry.is_current)?.node_id as usize;
+    let mut nodes = HashMap::with_capacity(entries.len());
+
+    for entry in entries {
+        let node = HistoryNode::new(
+            entry.parent_id.map(|parent| parent as usize),
+            entry.redo_child_id.map(|child| child as usize),
+            decode_operations(&entry.operations)?,
+            (
+                entry.cursor_before_column as usize,
+                entry.cursor_before_line as usize,
+            ),
+            (
+                entry.cursor_after_column as usize,
+                entry.cursor_after_line as usize,
+            ),
+        );
+        nodes.insert(entry.node_id as usize, node);
+    }
+
+    HistoryModel::from_nodes(nodes, current, &Rope::from_str(content))
+}
+
+// we're a JSON tool after all, operations are stored as
+// `[{"kind": "insert", "index": 4, "text": "a"}, {"kind": "replace", "old": "..", "new": ".."}]`
+fn encode_operations(operations: &[EditOperation]) -> String {
+    let encoded: Vec<String> = operations
+        .iter()
+        .map(|operation| match operation {
+            EditOperation::Insert { char_index, text } => format!(
+                "{{\"kind\":\"insert\",\"index\":{},\"text\":{}}}",
+                char_index,
+                escape_string(text)
+            ),
+            EditOperation::Remove { char_index, text } => format!(
+                "{{\"kind\":\"remove\",\"index\":{},\"text\":{}}}",
+                char_index,
+                escape_string(text)
+            ),
+            EditOperation::Replace { old, new } => format!(
+                "{{\"kind\":\"replace\",\"old\":{},\"new\":{}}}",
+                escape_string(&old.to_string()),
+                escape_string(&new.to_string())
+            ),
+        })
+        .collect();
+
+    format!("[{}]", encoded.join(","))
+}
+
+fn decode_operations(text: &str) -> Option<Vec<EditOperation>> {
+    let JsonValue::Array(items) = parse(text).ok()?.value else {
+        return None;
+    };
+
+    let get_string = |item: &JsonNode, key: &str| match &item.get_member(key)?.value {
+        JsonValue::String(value) => Some(value.clone()),
+        _ => None,
+    };
+    let get_index = |item: &JsonNode| match &item.get_member("index")?.value {
+        JsonValue::Number(number) => number.parse::<usize>().ok(),
+        _ => None,
+    };
+
+    items
+        .iter()
+        .map(|item| match get_string(item, "kind")?.as_str() {
+            "insert" => Some(EditOperation::Insert {
+                char_index: get_index(item)?,
+                text: get_string(item, "text")?,
+            }),
+            "remove" => Some(EditOperation::Remove {
+                char_index: get_index(item)?,
+                text: get_string(item, "text")?,
+            }),
+            "replace" => Some(EditOperation::Replace {
+                old: Rope::from_str(&get_string(item, "old")?),
+                new: Rope::from_str(&get_string(item, "new")?),
+            }),
+            _ => None,
+        })
+        .collect()
 }
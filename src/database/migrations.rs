@@ -7,6 +7,9 @@ pub trait ToSql {
 pub struct Migration {
     pub name: String,
     pub sql: String,
+    // sqlite has no ADD COLUMN IF NOT EXISTS and every migration runs on every start,
+    // so the table and column are kept to skip it once the column is there
+    pub added_column: Option<(String, String)>,
 }
 
 impl ToSql for Migration {
@@ -16,11 +19,35 @@ impl ToSql for Migration {
 }
 
 impl Migration {
+    pub fn add_column(table: &str, column: &str, definition: &str) -> Self {
+        Migration {
+            name: format!("Add column {} to {}", column, table),
+            sql: format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            added_column: Some((table.to_string(), column.to_string())),
+        }
+    }
+
     pub fn run(&self, connection: &Connection) {
+        if let Some((table, column)) = &self.added_column {
+            if has_column(connection, table, column) {
+                return;
+            }
+        }
         connection.execute(self.to_sql().as_str(), []).unwrap();
     }
 }
 
+fn has_column(connection: &Connection, table: &str, column: &str) -> bool {
+    connection
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+        > 0
+}
+
 pub fn get_migrations() -> Vec<Migration> {
     Vec::from([
         Migration {
@@ -30,11 +57,12 @@ pub fn get_migrations() -> Vec<Migration> {
                 project_id INTEGER NOT NULL,
                 name TEXT NOT NULL,
                 body TEXT NOT NULL,
-                url TEST NOT NULL, 
+                url TEXT NOT NULL,
                 additional_data TEXT NOT NULL,
-                headers TEXT NOT NULL,
+                headers TEXT NOT NULL
             )"
             .to_string(),
+            added_column: None,
         },
         Migration {
             name: "Create table jdiff_editor_content".to_string(),
@@ -44,6 +72,7 @@ pub fn get_migrations() -> Vec<Migration> {
                 content TEXT NOT NULL
             )"
             .to_string(),
+            added_column: None,
         },
         Migration {
             name: "Create table jdiff_projects".to_string(),
@@ -52,6 +81,74 @@ pub fn get_migrations() -> Vec<Migration> {
                 name TEXT NOT NULL
             )"
             .to_string(),
+            added_column: None,
+        },
+        Migration {
+            name: "Create table jdiff_diff_options".to_string(),
+            sql: "CREATE TABLE IF NOT EXISTS jdiff_diff_options (
+                id INTEGER PRIMARY KEY,
+                project_id INTEGER NOT NULL UNIQUE,
+                ignore_key_order INTEGER NOT NULL,
+                array_mode TEXT NOT NULL,
+                array_key TEXT NOT NULL,
+                ignore_whitespace INTEGER NOT NULL,
+                ignored_paths TEXT NOT NULL
+            )"
+            .to_string(),
+            added_column: None,
+        },
+        Migration::add_column(
+            "jdiff_diff_options",
+            "compare_numbers_by_value",
+            "INTEGER NOT NULL DEFAULT 0",
+        ),
+        Migration::add_column(
+            "jdiff_diff_options",
+            "absolute_epsilon",
+            "REAL NOT NULL DEFAULT 0",
+        ),
+        Migration::add_column(
+            "jdiff_diff_options",
+            "relative_epsilon",
+            "REAL NOT NULL DEFAULT 0",
+        ),
+        Migration::add_column(
+            "jdiff_diff_options",
+            "coerce_types",
+            "INTEGER NOT NULL DEFAULT 0",
+        ),
+        Migration {
+            name: "Create table jdiff_editor_history".to_string(),
+            sql: "CREATE TABLE IF NOT EXISTS jdiff_editor_history (
+                id INTEGER PRIMARY KEY,
+                editor_content_id INTEGER NOT NULL,
+                node_id INTEGER NOT NULL,
+                parent_id INTEGER,
+                redo_child_id INTEGER,
+                is_current INTEGER NOT NULL,
+                operations TEXT NOT NULL,
+                cursor_before_column INTEGER NOT NULL,
+                cursor_before_line INTEGER NOT NULL,
+                cursor_after_column INTEGER NOT NULL,
+                cursor_after_line INTEGER NOT NULL
+            )"
+            .to_string(),
+            added_column: None,
         },
+        Migration::add_column(
+            "jdiff_editor_content",
+            "history_key",
+            "TEXT NOT NULL DEFAULT ''",
+        ),
+        Migration::add_column(
+            "jdiff_editor_content",
+            "position",
+            "INTEGER NOT NULL DEFAULT 0",
+        ),
+        Migration::add_column(
+            "jdiff_editor_history",
+            "operation_count",
+            "INTEGER NOT NULL DEFAULT 0",
+        ),
     ])
 }
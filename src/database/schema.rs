@@ -8,14 +8,56 @@ pub struct Request {
     pub headers: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EditorContent {
     pub id: i32,
     pub project_id: i32,
     pub content: String,
+    // where the editor is shown, rows saved before there was one are in id order
+    pub position: i64,
+    // the key of the history saved with it, empty for rows saved before there was one
+    pub history_key: String,
+}
+
+// a single undo step of an editor, they make up a tree through parent_id
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorHistoryEntry {
+    pub id: i32,
+    pub editor_content_id: i32,
+    pub node_id: i64,
+    // None for the state the history starts from
+    pub parent_id: Option<i64>,
+    pub redo_child_id: Option<i64>,
+    pub is_current: bool,
+    // a JSON array, see db_manager
+    pub operations: String,
+    // steps only ever get operations added or all of them cleared, so this tells
+    // if the operations have to be written again
+    pub operation_count: i64,
+    pub cursor_before_column: i64,
+    pub cursor_before_line: i64,
+    pub cursor_after_column: i64,
+    pub cursor_after_line: i64,
 }
 
 pub struct Project {
     pub id: i32,
     pub name: String,
 }
+
+#[derive(Debug, Clone)]
+pub struct DiffSettings {
+    pub id: i32,
+    pub project_id: i32,
+    pub ignore_key_order: bool,
+    // "ordered", "unordered" or "key", in which case array_key holds the key
+    pub array_mode: String,
+    pub array_key: String,
+    pub ignore_whitespace: bool,
+    // one JSONPath expression per line
+    pub ignored_paths: String,
+    pub compare_numbers_by_value: bool,
+    pub absolute_epsilon: f64,
+    pub relative_epsilon: f64,
+    pub coerce_types: bool,
+}
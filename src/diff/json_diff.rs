@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+
+use crate::json::{
+    format::{escape_string, to_compact_string},
+    parser::is_valid_number,
+    value::{push_pointer_segment, split_pointer, JsonNode, JsonValue},
+};
+
+use super::{
+    numbers::{are_numbers_equal, get_number_key},
+    options::{ArrayMode, DiffOptions},
+    text_diff::{diff_lines, LineOperation},
+    Change, ChangeKind,
+};
+
+pub fn diff_nodes(left: &JsonNode, right: &JsonNode, options: &DiffOptions) -> Vec<Change> {
+    diff_nodes_at(left, right, String::new(), options)
+}
+
+// same thing for nodes somewhere inside the documents, `path` is their JSON pointer
+pub fn diff_nodes_at(
+    left: &JsonNode,
+    right: &JsonNode,
+    path: String,
+    options: &DiffOptions,
+) -> Vec<Change> {
+    let differ = JsonDiffer { options };
+    let mut changes = Vec::new();
+    differ.compare_nodes(left, right, path, &mut changes);
+    changes
+}
+
+struct JsonDiffer<'a> {
+    options: &'a DiffOptions,
+}
+
+impl<'a> JsonDiffer<'a> {
+    fn push(&self, changes: &mut Vec<Change>, change: Change) {
+        if !self.options.is_path_ignored(&change.path) {
+            changes.push(change);
+        }
+    }
+
+    // whether compare_nodes would find nothing, without building the changes. Stops
+    // at the first difference
+    fn is_equal(&self, left: &JsonNode, right: &JsonNode, path: &str) -> bool {
+        if self.options.is_path_ignored(path) {
+            return true;
+        }
+        let is_ignored = |index: usize| {
+            self.options
+                .is_path_ignored(&push_pointer_segment(path, &index.to_string()))
+        };
+
+        match (&left.value, &right.value) {
+            (JsonValue::Object(left_members), JsonValue::Object(right_members)) => {
+                self.are_objects_equal(left, right, left_members, right_members, path)
+            }
+            (JsonValue::Array(left_items), JsonValue::Array(right_items)) => {
+                let pairs = match &self.options.array_mode {
+                    ArrayMode::Ordered => (0..left_items.len().max(right_items.len()))
+                        .map(|index| {
+                            (
+                                Some(index).filter(|index| *index < left_items.len()),
+                                Some(index).filter(|index| *index < right_items.len()),
+                            )
+                        })
+                        .collect(),
+                    ArrayMode::Unordered => {
+                        let (is_left_matched, is_right_matched) =
+                            self.match_unordered_items(left_items, right_items, path);
+                        return (0..left_items.len())
+                            .all(|index| is_left_matched[index] || is_ignored(index))
+                            && (0..right_items.len())
+                                .all(|index| is_right_matched[index] || is_ignored(index));
+                    }
+                    ArrayMode::MatchByKey(key) => pair_items_by_key(left_items, right_items, key),
+                };
+                pairs.into_iter().all(|pair| match pair {
+                    (Some(left_index), Some(right_index)) => self.is_equal(
+                        &left_items[left_index],
+                        &right_items[right_index],
+                        &push_pointer_segment(path, &left_index.to_string()),
+                    ),
+                    (Some(index), None) | (None, Some(index)) => is_ignored(index),
+                    (None, None) => true,
+                })
+            }
+            (JsonValue::Number(left_number), JsonValue::Number(right_number))
+                if self.options.compare_numbers_by_value =>
+            {
+                are_numbers_equal(left_number, right_number, self.options)
+            }
+            (left_value, right_value) => left_value == right_value,
+        }
+    }
+
+    fn are_objects_equal(
+        &self,
+        left: &JsonNode,
+        right: &JsonNode,
+        left_members: &[(String, JsonNode)],
+        right_members: &[(String, JsonNode)],
+        path: &str,
+    ) -> bool {
+        let is_ignored = |key: &str| {
+            self.options
+                .is_path_ignored(&push_pointer_segment(path, key))
+        };
+
+        let are_members_equal =
+            left_members
+                .iter()
+                .all(|(key, left_member)| match right.get_member(key) {
+                    Some(right_member) => {
+                        self.is_equal(left_member, right_member, &push_pointer_segment(path, key))
+                    }
+                    None => is_ignored(key),
+                })
+                && right_members
+                    .iter()
+                    .all(|(key, _)| left.get_member(key).is_some() || is_ignored(key));
+        if !are_members_equal || self.options.ignore_key_order {
+            return are_members_equal;
+        }
+
+        let left_keys: Vec<&str> = left_members
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| right.get_member(key).is_some())
+            .collect();
+        let right_keys: Vec<&str> = right_members
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| left.get_member(key).is_some())
+            .collect();
+        diff_lines(&left_keys, &right_keys)
+            .into_iter()
+            .all(|operation| match operation {
+                LineOperation::Delete(index) => is_ignored(left_keys[index]),
+                _ => true,
+            })
+    }
+
+    fn compare_nodes(
+        &self,
+        left: &JsonNode,
+        right: &JsonNode,
+        path: String,
+        changes: &mut Vec<Change>,
+    ) {
+        if self.options.is_path_ignored(&path) {
+            return;
+        }
+
+        match (&left.value, &right.value) {
+            (JsonValue::Object(left_members), JsonValue::Object(right_members)) => {
+                self.compare_objects(left, right, left_members, right_members, &path, changes)
+            }
+            (JsonValue::Array(left_items), JsonValue::Array(right_items)) => {
+                match &self.options.array_mode {
+                    ArrayMode::Ordered => {
+                        self.compare_arrays_by_index(left_items, right_items, &path, changes)
+                    }
+                    ArrayMode::Unordered => {
+                        self.compare_arrays_unordered(left_items, right_items, &path, changes)
+                    }
+                    ArrayMode::MatchByKey(key) => {
+                        self.compare_arrays_by_key(left_items, right_items, key, &path, changes)
+                    }
+                }
+            }
+            (JsonValue::Number(left_number), JsonValue::Number(right_number))
+                if self.options.compare_numbers_by_value =>
+            {
+                if !are_numbers_equal(left_number, right_number, self.options) {
+                    self.push(
+                        changes,
+                        Change::modified(path, ChangeKind::Changed, left, right),
+                    );
+                }
+            }
+            (left_value, right_value) => {
+                if left_value.get_type_name() != right_value.get_type_name() {
+                    let kind = if self.options.coerce_types
+                        && self.is_coerced_equal(left_value, right_value)
+                    {
+                        ChangeKind::TypeOnly
+                    } else {
+                        ChangeKind::TypeChanged
+                    };
+                    self.push(changes, Change::modified(path, kind, left, right));
+                } else if left_value != right_value {
+                    self.push(
+                        changes,
+                        Change::modified(path, ChangeKind::Changed, left, right),
+                    );
+                }
+            }
+        }
+    }
+
+    // a string holding exactly what the other side has as a number, boolean or null
+    fn is_coerced_equal(&self, left: &JsonValue, right: &JsonValue) -> bool {
+        let (text, value) = match (left, right) {
+            (JsonValue::String(text), value) | (value, JsonValue::String(text)) => (text, value),
+            _ => return false,
+        };
+
+        match value {
+            JsonValue::Number(number) => {
+                let text = text.trim();
+                is_valid_number(text) && are_numbers_equal(text, number, self.options)
+            }
+            JsonValue::Bool(value) => text == if *value { "true" } else { "false" },
+            JsonValue::Null => text == "null",
+            _ => false,
+        }
+    }
+
+    fn compare_objects(
+        &self,
+        left: &JsonNode,
+        right: &JsonNode,
+        left_members: &[(String, JsonNode)],
+        right_members: &[(String, JsonNode)],
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) {
+        for (key, left_member) in left_members {
+            let member_path = push_pointer_segment(path, key);
+            match right.get_member(key) {
+                Some(right_member) => {
+                    self.compare_nodes(left_member, right_member, member_path, changes)
+                }
+                None => self.push(changes, Change::removed(member_path, left_member)),
+            }
+        }
+
+        for (key, right_member) in right_members {
+            if left.get_member(key).is_none() {
+                self.push(
+                    changes,
+                    Change::added(push_pointer_segment(path, key), right_member),
+                );
+            }
+        }
+
+        if self.options.ignore_key_order {
+            return;
+        }
+
+        // keys present on both sides that aren't part of the longest common ordering moved
+        let left_keys: Vec<&str> = left_members
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| right.get_member(key).is_some())
+            .collect();
+        let right_keys: Vec<&str> = right_members
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| left.get_member(key).is_some())
+            .collect();
+
+        for operation in diff_lines(&left_keys, &right_keys) {
+            if let LineOperation::Delete(index) = operation {
+                let key = left_keys[index];
+                if let (Some(left_member), Some(right_member)) =
+                    (left.get_member(key), right.get_member(key))
+                {
+                    self.push(
+                        changes,
+                        Change::modified(
+                            push_pointer_segment(path, key),
+                            ChangeKind::Changed,
+                            left_member,
+                            right_member,
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn compare_arrays_by_index(
+        &self,
+        left_items: &[JsonNode],
+        right_items: &[JsonNode],
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) {
+        for index in 0..left_items.len().max(right_items.len()) {
+            let item_path = push_pointer_segment(path, &index.to_string());
+            match (left_items.get(index), right_items.get(index)) {
+                (Some(left_item), Some(right_item)) => {
+                    self.compare_nodes(left_item, right_item, item_path, changes)
+                }
+                (Some(left_item), None) => {
+                    self.push(changes, Change::removed(item_path, left_item))
+                }
+                (None, Some(right_item)) => {
+                    self.push(changes, Change::added(item_path, right_item))
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    // every item gets paired with an equal one from the other side, whatever is left
+    // over was either added or removed. Paths use the index on the side the item is on
+    fn compare_arrays_unordered(
+        &self,
+        left_items: &[JsonNode],
+        right_items: &[JsonNode],
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) {
+        let (is_left_matched, is_right_matched) =
+            self.match_unordered_items(left_items, right_items, path);
+
+        for (left_index, left_item) in left_items.iter().enumerate() {
+            if !is_left_matched[left_index] {
+                self.push(
+                    changes,
+                    Change::removed(
+                        push_pointer_segment(path, &left_index.to_string()),
+                        left_item,
+                    ),
+                );
+            }
+        }
+
+        for (right_index, right_item) in right_items.iter().enumerate() {
+            if !is_right_matched[right_index] {
+                self.push(
+                    changes,
+                    Change::added(
+                        push_pointer_segment(path, &right_index.to_string()),
+                        right_item,
+                    ),
+                );
+            }
+        }
+    }
+
+    // each left item takes the first equal right item nobody took yet. Equal items
+    // always get the same key, so only the right items sharing it are compared
+    fn match_unordered_items(
+        &self,
+        left_items: &[JsonNode],
+        right_items: &[JsonNode],
+        path: &str,
+    ) -> (Vec<bool>, Vec<bool>) {
+        let mut is_left_matched = vec![false; left_items.len()];
+        let mut is_right_matched = vec![false; right_items.len()];
+
+        let pointer_segments = split_pointer(path);
+        let mut segments: Vec<Option<&str>> = pointer_segments
+            .iter()
+            .map(|segment| Some(segment.as_str()))
+            .collect();
+        segments.push(None);
+        // an ignored item equals anything, keys can't tell those apart
+        let is_any_item_ignored = self.could_ignore(&segments);
+
+        let mut get_key = |item| {
+            let mut key = String::new();
+            if !is_any_item_ignored {
+                self.write_item_key(item, &mut segments, &mut key);
+            }
+            key
+        };
+        let mut candidates: HashMap<String, Vec<usize>> = HashMap::new();
+        for (right_index, right_item) in right_items.iter().enumerate() {
+            candidates
+                .entry(get_key(right_item))
+                .or_default()
+                .push(right_index);
+        }
+
+        for (left_index, left_item) in left_items.iter().enumerate() {
+            let Some(right_indexes) = candidates.get_mut(&get_key(left_item)) else {
+                continue;
+            };
+            let item_path = push_pointer_segment(path, &left_index.to_string());
+            let matching_item = right_indexes.iter().position(|right_index| {
+                self.is_equal(left_item, &right_items[*right_index], &item_path)
+            });
+            if let Some(position) = matching_item {
+                is_left_matched[left_index] = true;
+                is_right_matched[right_indexes.remove(position)] = true;
+            }
+        }
+
+        (is_left_matched, is_right_matched)
+    }
+
+    fn could_ignore(&self, segments: &[Option<&str>]) -> bool {
+        !self.options.ignored_paths.is_empty() && self.options.could_ignore(segments)
+    }
+
+    // to_compact_string without anything that doesn't count, like ignored paths, key
+    // order or how numbers are written. `segments` is where the item is, array indexes
+    // are None since the item gets compared under the index of the other side
+    fn write_item_key<'n>(
+        &self,
+        node: &'n JsonNode,
+        segments: &mut Vec<Option<&'n str>>,
+        key: &mut String,
+    ) {
+        match &node.value {
+            JsonValue::Object(members) => {
+                let mut members: Vec<&'n (String, JsonNode)> = members.iter().collect();
+                // objects with a key twice only look at the first one
+                members.sort_by(|left, right| left.0.cmp(&right.0));
+                members.dedup_by(|right, left| left.0 == right.0);
+
+                key.push('{');
+                for (member_key, member) in members {
+                    segments.push(Some(member_key));
+                    if !self.could_ignore(segments) {
+                        key.push_str(&escape_string(member_key));
+                        key.push(':');
+                        self.write_item_key(member, segments, key);
+                        key.push(',');
+                    }
+                    segments.pop();
+                }
+                key.push('}');
+            }
+            JsonValue::Array(items) => {
+                segments.push(None);
+                // some of the items might not count, those arrays could equal any other
+                if self.could_ignore(segments) {
+                    key.push_str("[?]");
+                } else {
+                    let mut item_keys: Vec<String> = items
+                        .iter()
+                        .map(|item| {
+                            let mut item_key = String::new();
+                            self.write_item_key(item, segments, &mut item_key);
+                            item_key
+                        })
+                        .collect();
+                    if self.options.array_mode != ArrayMode::Ordered {
+                        item_keys.sort_unstable();
+                    }
+                    key.push('[');
+                    key.push_str(&item_keys.join(","));
+                    key.push(']');
+                }
+                segments.pop();
+            }
+            JsonValue::Number(number) if self.options.compare_numbers_by_value => {
+                key.push_str(&get_number_key(number, self.options))
+            }
+            _ => key.push_str(&to_compact_string(node)),
+        }
+    }
+
+    fn compare_arrays_by_key(
+        &self,
+        left_items: &[JsonNode],
+        right_items: &[JsonNode],
+        key: &str,
+        path: &str,
+        changes: &mut Vec<Change>,
+    ) {
+        for pair in pair_items_by_key(left_items, right_items, key) {
+            match pair {
+                (Some(left_index), Some(right_index)) => self.compare_nodes(
+                    &left_items[left_index],
+                    &right_items[right_index],
+                    push_pointer_segment(path, &left_index.to_string()),
+                    changes,
+                ),
+                (Some(left_index), None) => self.push(
+                    changes,
+                    Change::removed(
+                        push_pointer_segment(path, &left_index.to_string()),
+                        &left_items[left_index],
+                    ),
+                ),
+                (None, Some(right_index)) => self.push(
+                    changes,
+                    Change::added(
+                        push_pointer_segment(path, &right_index.to_string()),
+                        &right_items[right_index],
+                    ),
+                ),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+// items are paired by the value under `key`, items that don't have it are paired by
+// their position among each other. None on one side means the item was added or removed
+fn pair_items_by_key(
+    left_items: &[JsonNode],
+    right_items: &[JsonNode],
+    key: &str,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    let get_identity = |item: &JsonNode| item.get_member(key).map(to_compact_string);
+
+    let mut right_by_identity: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut right_without_identity = Vec::new();
+    for (right_index, right_item) in right_items.iter().enumerate().rev() {
+        match get_identity(right_item) {
+            Some(identity) => right_by_identity
+                .entry(identity)
+                .or_default()
+                .push(right_index),
+            None => right_without_identity.push(right_index),
+        }
+    }
+    right_without_identity.reverse();
+
+    let mut pairs = Vec::new();
+    let mut is_right_matched = vec![false; right_items.len()];
+    let mut left_without_identity = Vec::new();
+    for (left_index, left_item) in left_items.iter().enumerate() {
+        let Some(identity) = get_identity(left_item) else {
+            left_without_identity.push(left_index);
+            continue;
+        };
+        // the indexes are reversed, so the first one left is at the end
+        let matching_item = right_by_identity
+            .get_mut(&identity)
+            .and_then(|right_indexes| right_indexes.pop());
+        if let Some(right_index) = matching_item {
+            is_right_matched[right_index] = true;
+        }
+        pairs.push((Some(left_index), matching_item));
+    }
+
+    for (right_index, right_item) in right_items.iter().enumerate() {
+        if !is_right_matched[right_index] && get_identity(right_item).is_some() {
+            pairs.push((None, Some(right_index)));
+        }
+    }
+
+    for index in 0..left_without_identity
+        .len()
+        .max(right_without_identity.len())
+    {
+        pairs.push((
+            left_without_identity.get(index).copied(),
+            right_without_identity.get(index).copied(),
+        ));
+    }
+
+    pairs
+}
+
+impl Change {
+    fn added(path: String, node: &JsonNode) -> Self {
+        Change {
+            path,
+            kind: ChangeKind::Added,
+            old_value: None,
+            new_value: Some(to_compact_string(node)),
+            left_lines: None,
+            right_lines: Some((node.start_line, node.end_line)),
+        }
+    }
+
+    fn removed(path: String, node: &JsonNode) -> Self {
+        Change {
+            path,
+            kind: ChangeKind::Removed,
+            old_value: Some(to_compact_string(node)),
+            new_value: None,
+            left_lines: Some((node.start_line, node.end_line)),
+            right_lines: None,
+        }
+    }
+
+    fn modified(path: String, kind: ChangeKind, left: &JsonNode, right: &JsonNode) -> Self {
+        Change {
+            path,
+            kind,
+            old_value: Some(to_compact_string(left)),
+            new_value: Some(to_compact_string(right)),
+            left_lines: Some((left.start_line, left.end_line)),
+            right_lines: Some((right.start_line, right.end_line)),
+        }
+    }
+}
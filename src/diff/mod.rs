@@ -0,0 +1,584 @@
+use tracing::info;
+
+use crate::json::parser::parse;
+
+use self::{
+    options::{strip_whitespace, DiffOptions, DiffProjection},
+    text_diff::{diff_lines, LineOperation},
+};
+
+pub mod json_diff;
+pub mod numbers;
+pub mod options;
+pub mod text_diff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    // both editors hold valid JSON and got compared value by value
+    Structural,
+    // at least one of them didn't parse, so we fell back to comparing lines
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+    TypeChanged,
+    // the value is the same but the type isn't, like `"42"` and `42`
+    TypeOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
+impl DiffSide {
+    pub fn from_editor_index(editor_index: usize) -> Self {
+        if editor_index == 0 {
+            DiffSide::Left
+        } else {
+            DiffSide::Right
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    // JSON pointer, the root document is an empty string
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    // inclusive line ranges, 0 based, in the left and right editor
+    pub left_lines: Option<(usize, usize)>,
+    pub right_lines: Option<(usize, usize)>,
+}
+
+// a single row of the side by side view. Lines that have no counterpart on the
+// other side get paired with None, which is rendered as a filler line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRow {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+}
+
+impl DiffRow {
+    pub fn get_line(&self, side: DiffSide) -> Option<usize> {
+        match side {
+            DiffSide::Left => self.left,
+            DiffSide::Right => self.right,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffResult {
+    pub kind: DiffKind,
+    // only the structural diff knows about paths, for text diffs this is empty
+    pub changes: Vec<Change>,
+    // indexes into changes sorted by path, so a path can be looked up without going
+    // through every change
+    pub changes_by_path: Vec<usize>,
+    // status of every line in each editor, indexed by 0 based line
+    pub left_line_status: Vec<Option<ChangeKind>>,
+    pub right_line_status: Vec<Option<ChangeKind>>,
+    pub rows: Vec<DiffRow>,
+    // the row of every line in each editor, so finding one doesn't go through all the rows
+    left_rows: Vec<Option<usize>>,
+    right_rows: Vec<Option<usize>>,
+    // inclusive ranges of rows
+    pub hunks: Vec<(usize, usize)>,
+}
+
+impl DiffResult {
+    pub fn find_row(&self, side: DiffSide, line: usize) -> Option<usize> {
+        let rows = match side {
+            DiffSide::Left => &self.left_rows,
+            DiffSide::Right => &self.right_rows,
+        };
+        rows.get(line).copied().flatten()
+    }
+
+    fn index_rows(&mut self) {
+        self.left_rows = vec![None; self.left_line_status.len()];
+        self.right_rows = vec![None; self.right_line_status.len()];
+        for (index, row) in self.rows.iter().enumerate() {
+            if let Some(line) = row.left {
+                self.left_rows[line] = Some(index);
+            }
+            if let Some(line) = row.right {
+                self.right_rows[line] = Some(index);
+            }
+        }
+    }
+
+    // filler rows have no line on one of the sides, so this looks for the closest
+    // line below the row first, and then above it
+    pub fn find_line_near_row(&self, side: DiffSide, row: usize) -> Option<usize> {
+        self.rows
+            .iter()
+            .skip(row)
+            .find_map(|row| row.get_line(side))
+            .or_else(|| {
+                self.rows
+                    .iter()
+                    .take(row)
+                    .rev()
+                    .find_map(|row| row.get_line(side))
+            })
+    }
+
+    // what changed at `pointer`. A collapsed node also counts as changed when
+    // anything under it did
+    pub fn get_path_status(&self, pointer: &str, is_collapsed: bool) -> Option<ChangeKind> {
+        let find_first = |path: &str| {
+            let index = self
+                .changes_by_path
+                .partition_point(|index| self.changes[*index].path.as_str() < path);
+            self.changes_by_path
+                .get(index)
+                .map(|index| &self.changes[*index])
+        };
+
+        if let Some(change) = find_first(pointer).filter(|change| change.path == pointer) {
+            return Some(change.kind);
+        }
+        let descendant_prefix = format!("{}/", pointer);
+        find_first(&descendant_prefix)
+            .filter(|change| is_collapsed && change.path.starts_with(&descendant_prefix))
+            .map(|_| ChangeKind::Changed)
+    }
+
+    pub fn get_line_status(&self, side: DiffSide, line: usize) -> Option<ChangeKind> {
+        let line_status = match side {
+            DiffSide::Left => &self.left_line_status,
+            DiffSide::Right => &self.right_line_status,
+        };
+        line_status.get(line).copied().flatten()
+    }
+}
+
+pub fn compute_diff(left: &str, right: &str, options: &DiffOptions) -> DiffResult {
+    let left_lines: Vec<&str> = left.split('\n').collect();
+    let right_lines: Vec<&str> = right.split('\n').collect();
+
+    // the line diff is needed either way, even structural diffs are displayed
+    // aligned by the lines they have in common
+    let operations = find_line_operations(&left_lines, &right_lines, options);
+    let mut diff = align_lines(&operations, &left_lines, &right_lines);
+
+    match (parse(left), parse(right)) {
+        (Ok(left_document), Ok(right_document)) => {
+            let changes = json_diff::diff_nodes(&left_document, &right_document, options);
+            apply_changes(&mut diff, changes);
+        }
+        (Err(error), _) | (_, Err(error)) => {
+            info!("Falling back to a text diff: {}", error);
+        }
+    }
+
+    diff.index_rows();
+    diff.hunks = find_hunks(&diff);
+    diff
+}
+
+// only the subtrees at `projection` get compared. Rows are made for their lines only,
+// so whatever is around them isn't shown. There's no text fallback, both have to parse
+pub fn compute_projected_diff(
+    left: &str,
+    right: &str,
+    projection: &DiffProjection,
+    options: &DiffOptions,
+) -> Result<DiffResult, String> {
+    let left_document =
+        parse(left).map_err(|error| format!("the left editor is not valid JSON: {}", error))?;
+    let right_document =
+        parse(right).map_err(|error| format!("the right editor is not valid JSON: {}", error))?;
+    let no_match = |side_name: &str| {
+        format!(
+            "'{}' matches nothing in the {} editor",
+            projection.expression, side_name
+        )
+    };
+    let left_node = projection
+        .find(&left_document)
+        .ok_or_else(|| no_match("left"))?;
+    let right_node = projection
+        .find(&right_document)
+        .ok_or_else(|| no_match("right"))?;
+
+    let left_lines: Vec<&str> = left.split('\n').collect();
+    let right_lines: Vec<&str> = right.split('\n').collect();
+    let left_projected = &left_lines[left_node.start_line..=left_node.end_line];
+    let right_projected = &right_lines[right_node.start_line..=right_node.end_line];
+    let operations = find_line_operations(left_projected, right_projected, options);
+    let projected = align_lines(&operations, left_projected, right_projected);
+
+    let mut diff = DiffResult {
+        kind: DiffKind::Structural,
+        changes: Vec::new(),
+        changes_by_path: Vec::new(),
+        left_line_status: vec![None; left_lines.len()],
+        right_line_status: vec![None; right_lines.len()],
+        rows: projected
+            .rows
+            .iter()
+            .map(|row| DiffRow {
+                left: row.left.map(|line| line + left_node.start_line),
+                right: row.right.map(|line| line + right_node.start_line),
+            })
+            .collect(),
+        left_rows: Vec::new(),
+        right_rows: Vec::new(),
+        hunks: Vec::new(),
+    };
+    let changes =
+        json_diff::diff_nodes_at(left_node, right_node, projection.get_pointer(), options);
+    apply_changes(&mut diff, changes);
+
+    diff.index_rows();
+    diff.hunks = find_hunks(&diff);
+    Ok(diff)
+}
+
+fn find_line_operations(
+    left_lines: &[&str],
+    right_lines: &[&str],
+    options: &DiffOptions,
+) -> Vec<LineOperation> {
+    if options.ignore_whitespace {
+        let left_stripped: Vec<String> = left_lines
+            .iter()
+            .map(|line| strip_whitespace(line))
+            .collect();
+        let right_stripped: Vec<String> = right_lines
+            .iter()
+            .map(|line| strip_whitespace(line))
+            .collect();
+        diff_lines(
+            &left_stripped.iter().map(String::as_str).collect::<Vec<_>>(),
+            &right_stripped
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+        )
+    } else {
+        diff_lines(left_lines, right_lines)
+    }
+}
+
+// the structural changes decide which lines are highlighted, not the line diff
+fn apply_changes(diff: &mut DiffResult, changes: Vec<Change>) {
+    diff.left_line_status.fill(None);
+    diff.right_line_status.fill(None);
+    for change in &changes {
+        if let Some(lines) = change.left_lines {
+            mark_lines(&mut diff.left_line_status, lines, change.kind);
+        }
+        if let Some(lines) = change.right_lines {
+            mark_lines(&mut diff.right_line_status, lines, change.kind);
+        }
+    }
+
+    diff.kind = DiffKind::Structural;
+    diff.changes_by_path = (0..changes.len()).collect();
+    diff.changes_by_path
+        .sort_by(|left, right| changes[*left].path.cmp(&changes[*right].path));
+    diff.changes = changes;
+}
+
+// a hunk is a run of rows where at least one of the sides differs
+fn find_hunks(diff: &DiffResult) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    for (index, row) in diff.rows.iter().enumerate() {
+        let is_different = [DiffSide::Left, DiffSide::Right].iter().any(|side| {
+            row.get_line(*side)
+                .is_none_or(|line| diff.get_line_status(*side, line).is_some())
+        });
+        if !is_different {
+            continue;
+        }
+
+        match hunks.last_mut() {
+            Some(hunk) if hunk.1 + 1 == index => hunk.1 = index,
+            _ => hunks.push((index, index)),
+        }
+    }
+
+    hunks
+}
+
+fn align_lines(
+    operations: &[LineOperation],
+    left_lines: &[&str],
+    right_lines: &[&str],
+) -> DiffResult {
+    let mut result = DiffResult {
+        kind: DiffKind::Text,
+        changes: Vec::new(),
+        changes_by_path: Vec::new(),
+        left_line_status: vec![None; left_lines.len()],
+        right_line_status: vec![None; right_lines.len()],
+        rows: Vec::with_capacity(left_lines.len().max(right_lines.len())),
+        left_rows: Vec::new(),
+        right_rows: Vec::new(),
+        hunks: Vec::new(),
+    };
+
+    let mut deleted: Vec<usize> = Vec::new();
+    let mut inserted: Vec<usize> = Vec::new();
+
+    for operation in operations {
+        match *operation {
+            LineOperation::Delete(left_index) => deleted.push(left_index),
+            LineOperation::Insert(right_index) => inserted.push(right_index),
+            LineOperation::Equal(left_index, right_index) => {
+                flush_hunk(
+                    &mut result,
+                    left_lines,
+                    right_lines,
+                    &mut deleted,
+                    &mut inserted,
+                );
+                result.rows.push(DiffRow {
+                    left: Some(left_index),
+                    right: Some(right_index),
+                });
+            }
+        }
+    }
+    flush_hunk(
+        &mut result,
+        left_lines,
+        right_lines,
+        &mut deleted,
+        &mut inserted,
+    );
+
+    result
+}
+
+// lines removed and added in the same spot are paired up and treated as changed,
+// whatever is left over on the longer side gets a filler line on the other one.
+// Lines sharing a key (whatever comes before the first ':') are paired first, so that
+// `"b": 2` ends up next to `"b": 3` even if something got inserted above it
+fn flush_hunk(
+    result: &mut DiffResult,
+    left_lines: &[&str],
+    right_lines: &[&str],
+    deleted: &mut Vec<usize>,
+    inserted: &mut Vec<usize>,
+) {
+    if deleted.is_empty() && inserted.is_empty() {
+        return;
+    }
+
+    let left_keys: Vec<String> = deleted
+        .iter()
+        .map(|line| get_line_key(left_lines[*line]))
+        .collect();
+    let right_keys: Vec<String> = inserted
+        .iter()
+        .map(|line| get_line_key(right_lines[*line]))
+        .collect();
+    let left_keys: Vec<&str> = left_keys.iter().map(String::as_str).collect();
+    let right_keys: Vec<&str> = right_keys.iter().map(String::as_str).collect();
+
+    let mut unmatched_deleted: Vec<usize> = Vec::new();
+    let mut unmatched_inserted: Vec<usize> = Vec::new();
+    for operation in diff_lines(&left_keys, &right_keys) {
+        match operation {
+            LineOperation::Delete(index) => unmatched_deleted.push(deleted[index]),
+            LineOperation::Insert(index) => unmatched_inserted.push(inserted[index]),
+            LineOperation::Equal(left_index, right_index) => {
+                pair_positionally(result, &unmatched_deleted, &unmatched_inserted);
+                unmatched_deleted.clear();
+                unmatched_inserted.clear();
+                pair_positionally(result, &[deleted[left_index]], &[inserted[right_index]]);
+            }
+        }
+    }
+    pair_positionally(result, &unmatched_deleted, &unmatched_inserted);
+
+    deleted.clear();
+    inserted.clear();
+}
+
+fn pair_positionally(result: &mut DiffResult, deleted: &[usize], inserted: &[usize]) {
+    let paired = deleted.len().min(inserted.len());
+
+    for index in 0..deleted.len().max(inserted.len()) {
+        let left = deleted.get(index).copied();
+        let right = inserted.get(index).copied();
+
+        if let Some(left_index) = left {
+            result.left_line_status[left_index] = Some(if index < paired {
+                ChangeKind::Changed
+            } else {
+                ChangeKind::Removed
+            });
+        }
+        if let Some(right_index) = right {
+            result.right_line_status[right_index] = Some(if index < paired {
+                ChangeKind::Changed
+            } else {
+                ChangeKind::Added
+            });
+        }
+
+        result.rows.push(DiffRow { left, right });
+    }
+}
+
+fn get_line_key(line: &str) -> String {
+    let line = line.trim();
+    match line.split_once(':') {
+        Some((key, _)) => key.trim_end().to_string(),
+        None => line.to_string(),
+    }
+}
+
+fn mark_lines(line_status: &mut [Option<ChangeKind>], lines: (usize, usize), kind: ChangeKind) {
+    for status in line_status.iter_mut().take(lines.1 + 1).skip(lines.0) {
+        // compact documents can have multiple changes on a single line,
+        // if they don't agree we just say the line has changed
+        *status = match status {
+            Some(existing) if *existing != kind => Some(ChangeKind::Changed),
+            _ => Some(kind),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_changes(diff: &DiffResult) -> Vec<(&str, ChangeKind)> {
+        diff.changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.kind))
+            .collect()
+    }
+
+    #[test]
+    fn compares_valid_documents_by_value() {
+        let left = "{\n  \"a\": 1,\n  \"b\": \"x\",\n  \"c\": true\n}";
+        let right = "{\n  \"c\": true,\n  \"a\": 2,\n  \"b\": 3,\n  \"d\": null\n}";
+        let diff = compute_diff(left, right, &DiffOptions::default());
+
+        assert_eq!(diff.kind, DiffKind::Structural);
+        let mut changes = get_changes(&diff);
+        changes.sort_by_key(|(path, _)| *path);
+        assert_eq!(
+            changes,
+            vec![
+                ("/a", ChangeKind::Changed),
+                ("/b", ChangeKind::TypeChanged),
+                ("/d", ChangeKind::Added),
+            ]
+        );
+        // the key order doesn't count, `c` moving isn't a change
+        assert_eq!(diff.get_line_status(DiffSide::Left, 3), None);
+        assert_eq!(diff.get_line_status(DiffSide::Right, 1), None);
+        assert_eq!(
+            diff.get_line_status(DiffSide::Right, 2),
+            Some(ChangeKind::Changed)
+        );
+        assert_eq!(
+            diff.get_line_status(DiffSide::Right, 4),
+            Some(ChangeKind::Added)
+        );
+        assert_eq!(diff.get_path_status("/a", false), Some(ChangeKind::Changed));
+        assert_eq!(diff.get_path_status("", true), Some(ChangeKind::Changed));
+        assert_eq!(diff.get_path_status("", false), None);
+    }
+
+    #[test]
+    fn falls_back_to_lines_when_a_side_doesnt_parse() {
+        let left = "{\n  \"a\": 1,\n  \"b\": 2\n}";
+        let right = "{\n  \"a\": 1,\n  \"b\": 3,\n  \"c\"\n}";
+        let diff = compute_diff(left, right, &DiffOptions::default());
+
+        assert_eq!(diff.kind, DiffKind::Text);
+        assert!(diff.changes.is_empty());
+        assert_eq!(
+            diff.left_line_status,
+            vec![None, None, Some(ChangeKind::Changed), None]
+        );
+        assert_eq!(
+            diff.right_line_status,
+            vec![
+                None,
+                None,
+                Some(ChangeKind::Changed),
+                Some(ChangeKind::Added),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_up_rows_and_groups_them_into_hunks() {
+        let left = "[\n  1,\n  2,\n  3,\n  4,\n  5\n]";
+        let right = "[\n  1,\n  9,\n  3,\n  4,\n  5,\n  6\n]";
+        let diff = compute_diff(left, right, &DiffOptions::default());
+
+        let rows: Vec<(Option<usize>, Option<usize>)> =
+            diff.rows.iter().map(|row| (row.left, row.right)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (Some(2), Some(2)),
+                (Some(3), Some(3)),
+                (Some(4), Some(4)),
+                (Some(5), Some(5)),
+                (None, Some(6)),
+                (Some(6), Some(7)),
+            ]
+        );
+        // `5` gained a comma but only its value counts
+        assert_eq!(diff.hunks, vec![(2, 2), (6, 6)]);
+        assert_eq!(diff.find_line_near_row(DiffSide::Left, 6), Some(6));
+    }
+
+    #[test]
+    fn whitespace_only_changes_can_be_ignored() {
+        // the trailing comma keeps it a text diff, where lines have to match exactly
+        let left = "{\n  \"a\": [1, 2],\n  \"b\": \"x y\",\n}";
+        let right = "{\n  \"a\":[1,2],\n  \"b\": \"x  y\",\n}";
+        let options = DiffOptions {
+            ignore_whitespace: true,
+            ..DiffOptions::default()
+        };
+
+        let diff = compute_diff(left, right, &DiffOptions::default());
+        assert_eq!(diff.kind, DiffKind::Text);
+        assert_eq!(diff.hunks, vec![(1, 2)]);
+
+        let diff = compute_diff(left, right, &options);
+        assert_eq!(diff.hunks, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn unordered_arrays_pair_items_that_only_differ_in_what_doesnt_count() {
+        let left = r#"[{"id": 1, "at": "x", "v": 1.0}, {"id": 2, "at": "y"}, 3, 3]"#;
+        let right = r#"[3, {"id": 2, "at": "z"}, {"v": 1, "at": "q", "id": 1}, 4]"#;
+        let mut options = DiffOptions::default();
+        for setting in ["arrays=unordered", "numeric", "ignore=$[*].at"] {
+            options.apply(setting).unwrap();
+        }
+
+        let diff = compute_diff(left, right, &options);
+        assert_eq!(
+            get_changes(&diff),
+            vec![("/3", ChangeKind::Removed), ("/3", ChangeKind::Added)]
+        );
+    }
+}
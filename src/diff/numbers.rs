@@ -0,0 +1,148 @@
+use super::options::DiffOptions;
+
+// a number reduced to sign, significant digits and exponent, so that `1`, `1.0`,
+// `10e-1` and `1E0` all end up the same. Comparing these instead of f64s keeps
+// big integers exact
+#[derive(Debug, PartialEq, Eq)]
+struct DecimalNumber {
+    is_negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+// expects something that already passed as a JSON number
+fn to_decimal(raw: &str) -> Option<DecimalNumber> {
+    let (is_negative, unsigned) = match raw.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, raw),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (unsigned, 0),
+    };
+    let (integer_part, fraction_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let all_digits = format!("{}{}", integer_part, fraction_part);
+    let leading_zeros = all_digits.chars().take_while(|c| *c == '0').count();
+    let significant = all_digits[leading_zeros..].trim_end_matches('0');
+
+    if significant.is_empty() {
+        // -0 and 0 are the same thing
+        return Some(DecimalNumber {
+            is_negative: false,
+            digits: String::new(),
+            exponent: 0,
+        });
+    }
+
+    // exponent of the first significant digit, as in 0.d1d2d3 * 10^exponent. Exponents
+    // near the i64 limits are valid JSON, those are only compared as written
+    let exponent = exponent
+        .checked_add(integer_part.len() as i64)?
+        .checked_sub(leading_zeros as i64)?;
+    Some(DecimalNumber {
+        is_negative,
+        digits: significant.to_string(),
+        exponent,
+    })
+}
+
+pub fn are_numbers_equal(left: &str, right: &str, options: &DiffOptions) -> bool {
+    if left == right {
+        return true;
+    }
+
+    if let (Some(left_decimal), Some(right_decimal)) = (to_decimal(left), to_decimal(right)) {
+        if left_decimal == right_decimal {
+            return true;
+        }
+    }
+
+    if options.absolute_epsilon <= 0.0 && options.relative_epsilon <= 0.0 {
+        return false;
+    }
+
+    let (Ok(left_value), Ok(right_value)) = (left.parse::<f64>(), right.parse::<f64>()) else {
+        return false;
+    };
+    let difference = (left_value - right_value).abs();
+    let largest = left_value.abs().max(right_value.abs());
+
+    difference <= options.absolute_epsilon || difference <= options.relative_epsilon * largest
+}
+
+// numbers are_numbers_equal finds equal get the same key. An epsilon makes that
+// equality fuzzy, so then every number shares one
+pub fn get_number_key(raw: &str, options: &DiffOptions) -> String {
+    let is_exact = options.absolute_epsilon <= 0.0 && options.relative_epsilon <= 0.0;
+    if !is_exact {
+        return "#".into();
+    }
+    match to_decimal(raw) {
+        Some(decimal) => format!(
+            "{}{}e{}",
+            if decimal.is_negative { "-" } else { "" },
+            decimal.digits,
+            decimal.exponent
+        ),
+        None => raw.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_number_written_differently_is_equal() {
+        let one = to_decimal("1");
+        for raw in ["1.0", "10e-1", "1E0", "0.001e3", "00.1e1"] {
+            assert_eq!(to_decimal(raw), one, "{}", raw);
+        }
+        assert_eq!(to_decimal("-0.0"), to_decimal("0"));
+        assert_ne!(to_decimal("-1"), one);
+        assert_ne!(to_decimal("1.5"), to_decimal("15"));
+    }
+
+    #[test]
+    fn big_integers_stay_exact() {
+        assert_ne!(
+            to_decimal("9007199254740993"),
+            to_decimal("9007199254740992")
+        );
+        assert_eq!(
+            to_decimal("90071992547409930"),
+            to_decimal("9007199254740993e1")
+        );
+    }
+
+    #[test]
+    fn huge_exponents_dont_overflow() {
+        assert_eq!(to_decimal("10e9223372036854775807"), None);
+        assert_eq!(to_decimal("1e99999999999999999999"), None);
+        assert!(to_decimal("0.01e-9223372036854775808").is_none());
+        assert!(are_numbers_equal(
+            "10e9223372036854775807",
+            "10e9223372036854775807",
+            &DiffOptions::default()
+        ));
+    }
+
+    #[test]
+    fn epsilons_allow_small_differences() {
+        let options = DiffOptions {
+            absolute_epsilon: 0.01,
+            ..DiffOptions::default()
+        };
+        assert!(are_numbers_equal("1.005", "1", &options));
+        assert!(!are_numbers_equal("1.02", "1", &options));
+
+        let options = DiffOptions {
+            relative_epsilon: 1e-3,
+            ..DiffOptions::default()
+        };
+        assert!(are_numbers_equal("1000", "1000.5", &options));
+        assert!(!are_numbers_equal("1", "1.5", &options));
+        assert!(!are_numbers_equal("1", "1.0001", &DiffOptions::default()));
+    }
+}
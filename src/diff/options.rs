@@ -0,0 +1,365 @@
+use std::fmt::Display;
+
+use crate::json::{
+    query::{parse_query, QuerySegment},
+    tokenizer::tokenize_line,
+    value::{push_pointer_segment, split_pointer, JsonNode, JsonValue},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayMode {
+    // items are compared index by index
+    Ordered,
+    // items are matched with an equal item anywhere in the other array
+    Unordered,
+    // object items are matched by the value under this key, e.g. `id`
+    MatchByKey(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoredPath {
+    // kept as typed so it can be shown and saved back exactly like that
+    pub expression: String,
+    pub segments: Vec<PathSegment>,
+}
+
+// the subtree both documents get narrowed down to before diffing, like `$.data.users`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffProjection {
+    pub expression: String,
+    pub segments: Vec<PathSegment>,
+}
+
+impl DiffProjection {
+    pub fn find<'a>(&self, document: &'a JsonNode) -> Option<&'a JsonNode> {
+        self.segments
+            .iter()
+            .try_fold(document, |node, segment| match (segment, &node.value) {
+                (PathSegment::Key(key), JsonValue::Object(_)) => node.get_member(key),
+                (PathSegment::Index(index), JsonValue::Array(items)) => items.get(*index),
+                _ => None,
+            })
+    }
+
+    // changes inside the subtree are still reported from the root of the document,
+    // so ignored paths work the same with or without a projection
+    pub fn get_pointer(&self) -> String {
+        self.segments
+            .iter()
+            .fold(String::new(), |pointer, segment| match segment {
+                PathSegment::Key(key) => push_pointer_segment(&pointer, key),
+                PathSegment::Index(index) => push_pointer_segment(&pointer, &index.to_string()),
+                PathSegment::Wildcard => pointer,
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    pub ignore_key_order: bool,
+    pub array_mode: ArrayMode,
+    pub ignore_whitespace: bool,
+    pub ignored_paths: Vec<IgnoredPath>,
+    // compare numbers by their value instead of how they were written
+    pub compare_numbers_by_value: bool,
+    // both only apply when comparing by value, 0 turns them off
+    pub absolute_epsilon: f64,
+    pub relative_epsilon: f64,
+    // `"42"` and `42` are reported as a type only change instead of a type change
+    pub coerce_types: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            ignore_key_order: true,
+            array_mode: ArrayMode::Ordered,
+            ignore_whitespace: false,
+            ignored_paths: Vec::new(),
+            compare_numbers_by_value: false,
+            absolute_epsilon: 0.0,
+            relative_epsilon: 0.0,
+            coerce_types: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionError {
+    pub message: String,
+}
+
+impl Display for OptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for OptionError {}
+
+impl OptionError {
+    fn new(message: String) -> Self {
+        OptionError { message }
+    }
+}
+
+impl DiffOptions {
+    // takes whatever comes after `:set`, vim style: `ignorewhitespace`, `noignorewhitespace`,
+    // `arrays=unordered`, `arraykey=id`, `ignore=$.meta.timestamp`, `noignore=...`,
+    // `numeric`, `epsilon=0.001`, `relepsilon=1e-6`, `coercetypes`
+    pub fn apply(&mut self, setting: &str) -> Result<(), OptionError> {
+        let (name, value) = match setting.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (setting.trim(), None),
+        };
+
+        match (name, value) {
+            ("ignorekeyorder", None) => self.ignore_key_order = true,
+            ("noignorekeyorder", None) => self.ignore_key_order = false,
+            ("ignorewhitespace", None) => self.ignore_whitespace = true,
+            ("noignorewhitespace", None) => self.ignore_whitespace = false,
+            ("arrays", Some("ordered")) => self.array_mode = ArrayMode::Ordered,
+            ("arrays", Some("unordered")) => self.array_mode = ArrayMode::Unordered,
+            ("arraykey", Some("")) => self.array_mode = ArrayMode::Ordered,
+            ("arraykey", Some(key)) => self.array_mode = ArrayMode::MatchByKey(key.into()),
+            ("ignore", Some(expression)) => {
+                let ignored_path = parse_ignored_path(expression)?;
+                if !self.ignored_paths.contains(&ignored_path) {
+                    self.ignored_paths.push(ignored_path);
+                }
+            }
+            ("noignore", None) => self.ignored_paths.clear(),
+            ("noignore", Some(expression)) => self
+                .ignored_paths
+                .retain(|ignored_path| ignored_path.expression != expression),
+            ("numeric", None) => self.compare_numbers_by_value = true,
+            ("nonumeric", None) => self.compare_numbers_by_value = false,
+            ("epsilon", Some(value)) => {
+                self.absolute_epsilon = parse_epsilon(value)?;
+                self.compare_numbers_by_value = true;
+            }
+            ("relepsilon", Some(value)) => {
+                self.relative_epsilon = parse_epsilon(value)?;
+                self.compare_numbers_by_value = true;
+            }
+            ("coercetypes", None) => self.coerce_types = true,
+            ("nocoercetypes", None) => self.coerce_types = false,
+            _ => return Err(OptionError::new(format!("Unknown option '{}'", setting))),
+        }
+
+        Ok(())
+    }
+
+    pub fn is_path_ignored(&self, pointer: &str) -> bool {
+        if self.ignored_paths.is_empty() {
+            return false;
+        }
+
+        let pointer_segments = split_pointer(pointer);
+        let pointer_segments: Vec<Option<&str>> = pointer_segments
+            .iter()
+            .map(|segment| Some(segment.as_str()))
+            .collect();
+        self.could_ignore(&pointer_segments)
+    }
+
+    // None segments are array indexes that could be anything, so this tells whether
+    // the path is ignored for some index there
+    pub fn could_ignore(&self, pointer_segments: &[Option<&str>]) -> bool {
+        // ignoring a path also ignores everything below it
+        self.ignored_paths.iter().any(|ignored_path| {
+            ignored_path.segments.len() <= pointer_segments.len()
+                && ignored_path
+                    .segments
+                    .iter()
+                    .zip(pointer_segments.iter())
+                    .all(
+                        |(segment, pointer_segment)| match (segment, pointer_segment) {
+                            (_, None) | (PathSegment::Wildcard, _) => true,
+                            (PathSegment::Key(key), Some(pointer_segment)) => {
+                                key == pointer_segment
+                            }
+                            (PathSegment::Index(index), Some(pointer_segment)) => {
+                                index.to_string() == *pointer_segment
+                            }
+                        },
+                    )
+        })
+    }
+}
+
+fn parse_epsilon(value: &str) -> Result<f64, OptionError> {
+    match value.parse::<f64>() {
+        Ok(epsilon) if epsilon.is_finite() && epsilon >= 0.0 => Ok(epsilon),
+        _ => Err(OptionError::new(format!(
+            "Expected a positive number for the epsilon but found '{}'",
+            value
+        ))),
+    }
+}
+
+// same paths as :query, minus the ones that can't be told from a single pointer
+pub fn parse_ignored_path(expression: &str) -> Result<IgnoredPath, OptionError> {
+    Ok(IgnoredPath {
+        expression: expression.to_string(),
+        segments: parse_path_segments(expression, true)?,
+    })
+}
+
+// a single subtree on each side, so no wildcards
+pub fn parse_projection(expression: &str) -> Result<DiffProjection, OptionError> {
+    Ok(DiffProjection {
+        expression: expression.to_string(),
+        segments: parse_path_segments(expression, false)?,
+    })
+}
+
+fn parse_path_segments(
+    expression: &str,
+    is_wildcard_allowed: bool,
+) -> Result<Vec<PathSegment>, OptionError> {
+    parse_query(expression)
+        .map_err(|error| OptionError::new(error.message))?
+        .into_iter()
+        .map(|segment| match segment {
+            QuerySegment::Key(key) => Ok(PathSegment::Key(key)),
+            QuerySegment::Index(index) if index >= 0 => Ok(PathSegment::Index(index as usize)),
+            QuerySegment::Wildcard if is_wildcard_allowed => Ok(PathSegment::Wildcard),
+            _ if is_wildcard_allowed => Err(OptionError::new(format!(
+                "Only keys, positive indexes and '*' can be ignored in path '{}'",
+                expression
+            ))),
+            _ => Err(OptionError::new(format!(
+                "Only keys and positive indexes can pick what gets diffed in path '{}'",
+                expression
+            ))),
+        })
+        .collect()
+}
+
+// `diff -w` semantics for JSON: any amount of whitespace between tokens is irrelevant,
+// but inside a string it's part of the value. The tokens get a single space between
+// them so `1 2` doesn't turn into `12`
+pub fn strip_whitespace(line: &str) -> String {
+    tokenize_line(line, 0)
+        .into_iter()
+        .map(|token| token.text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parser::parse;
+
+    #[test]
+    fn settings_go_on_and_off_vim_style() {
+        let mut options = DiffOptions::default();
+        options.apply("noignorekeyorder").unwrap();
+        options.apply("ignorewhitespace").unwrap();
+        options.apply("arraykey = id").unwrap();
+        options.apply("epsilon=0.5").unwrap();
+        options.apply("coercetypes").unwrap();
+        assert_eq!(
+            options,
+            DiffOptions {
+                ignore_key_order: false,
+                array_mode: ArrayMode::MatchByKey("id".into()),
+                ignore_whitespace: true,
+                compare_numbers_by_value: true,
+                absolute_epsilon: 0.5,
+                coerce_types: true,
+                ..DiffOptions::default()
+            }
+        );
+
+        options.apply("arraykey=").unwrap();
+        options.apply("nocoercetypes").unwrap();
+        assert_eq!(options.array_mode, ArrayMode::Ordered);
+        assert!(!options.coerce_types);
+    }
+
+    #[test]
+    fn bad_settings_are_rejected() {
+        let mut options = DiffOptions::default();
+        assert_eq!(
+            options.apply("bogus").unwrap_err().message,
+            "Unknown option 'bogus'"
+        );
+        assert!(options.apply("arrays=sideways").is_err());
+        assert!(options.apply("numeric=1").is_err());
+        assert!(options.apply("epsilon=-1").is_err());
+        assert!(options.apply("relepsilon=inf").is_err());
+        assert!(options.apply("ignore=$.a[").is_err());
+        assert_eq!(options, DiffOptions::default());
+    }
+
+    #[test]
+    fn ignored_paths_cover_everything_below_them() {
+        let mut options = DiffOptions::default();
+        options.apply("ignore=$.meta.timestamp").unwrap();
+        options.apply("ignore=$.items[*].id").unwrap();
+        options.apply("ignore=$.items[*].id").unwrap();
+        assert_eq!(options.ignored_paths.len(), 2);
+
+        assert!(options.is_path_ignored("/meta/timestamp"));
+        assert!(options.is_path_ignored("/meta/timestamp/nested"));
+        assert!(!options.is_path_ignored("/meta"));
+        assert!(options.is_path_ignored("/items/3/id"));
+        assert!(!options.is_path_ignored("/items/3/name"));
+
+        options.apply("noignore=$.meta.timestamp").unwrap();
+        assert!(!options.is_path_ignored("/meta/timestamp"));
+        options.apply("noignore").unwrap();
+        assert!(options.ignored_paths.is_empty());
+    }
+
+    #[test]
+    fn projections_pick_a_single_subtree() {
+        assert!(parse_ignored_path("$.items[*]").is_ok());
+        assert!(parse_ignored_path("$.items[-1]").is_err());
+        assert!(parse_projection("$.items[*]").is_err());
+        assert!(parse_projection("$..id").is_err());
+
+        let projection = parse_projection("$.data['a/b'][1]").unwrap();
+        assert_eq!(projection.get_pointer(), "/data/a~1b/1");
+        let document = parse("{\"data\": {\"a/b\": [1, {\"c\": 2}]}}").unwrap();
+        assert_eq!(
+            projection.find(&document).map(|node| &node.value),
+            Some(&JsonValue::Object(vec![(
+                "c".into(),
+                JsonNode::new(JsonValue::Number("2".into()), 0, 0)
+            )]))
+        );
+        assert!(parse_projection("$.data.missing")
+            .unwrap()
+            .find(&document)
+            .is_none());
+    }
+
+    #[test]
+    fn whitespace_between_tokens_doesnt_count() {
+        assert_eq!(
+            strip_whitespace("  \"a\" :\t[1,2 ] "),
+            strip_whitespace("\"a\":[1, 2]")
+        );
+        assert_ne!(strip_whitespace("[1 2]"), strip_whitespace("[12]"));
+    }
+
+    #[test]
+    fn whitespace_inside_strings_does() {
+        assert_ne!(strip_whitespace("\"a b\""), strip_whitespace("\"ab\""));
+        assert_ne!(
+            strip_whitespace("\"k\": \"a  b\""),
+            strip_whitespace("\"k\": \"a b\"")
+        );
+    }
+}
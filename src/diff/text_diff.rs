@@ -0,0 +1,296 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOperation {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// Myers' O(ND) diff, see "An O(ND) Difference Algorithm and Its Variations". Keeping
+// the path of every D around costs O(D²) memory, which two big unrelated inputs make
+// quadratic, so this is the linear space version from section 4b: find the middle
+// snake of the shortest path, then diff what's before and after it the same way
+pub fn diff_lines(left: &[&str], right: &[&str]) -> Vec<LineOperation> {
+    let mut operations = Vec::with_capacity(left.len().max(right.len()));
+    diff_range(left, right, (0, 0), &mut operations);
+    operations
+}
+
+// `offset` is where the slices start in the whole input, operations use those indexes
+fn diff_range(
+    left: &[&str],
+    right: &[&str],
+    offset: (usize, usize),
+    operations: &mut Vec<LineOperation>,
+) {
+    // two versions of the same payload are mostly identical, the common prefix and
+    // suffix keep D small and every split strips its own
+    let prefix = left
+        .iter()
+        .zip(right.iter())
+        .take_while(|(left_line, right_line)| left_line == right_line)
+        .count();
+    let suffix = left[prefix..]
+        .iter()
+        .rev()
+        .zip(right[prefix..].iter().rev())
+        .take_while(|(left_line, right_line)| left_line == right_line)
+        .count();
+
+    operations
+        .extend((0..prefix).map(|index| LineOperation::Equal(offset.0 + index, offset.1 + index)));
+
+    let left_middle = &left[prefix..left.len() - suffix];
+    let right_middle = &right[prefix..right.len() - suffix];
+    let middle_offset = (offset.0 + prefix, offset.1 + prefix);
+    if left_middle.is_empty() {
+        operations.extend(
+            (0..right_middle.len()).map(|index| LineOperation::Insert(middle_offset.1 + index)),
+        );
+    } else if right_middle.is_empty() {
+        operations.extend(
+            (0..left_middle.len()).map(|index| LineOperation::Delete(middle_offset.0 + index)),
+        );
+    } else {
+        let (start, end) = find_middle_snake(left_middle, right_middle);
+        diff_range(
+            &left_middle[..start.0],
+            &right_middle[..start.1],
+            middle_offset,
+            operations,
+        );
+        operations.extend((0..end.0 - start.0).map(|index| {
+            LineOperation::Equal(
+                middle_offset.0 + start.0 + index,
+                middle_offset.1 + start.1 + index,
+            )
+        }));
+        diff_range(
+            &left_middle[end.0..],
+            &right_middle[end.1..],
+            (middle_offset.0 + end.0, middle_offset.1 + end.1),
+            operations,
+        );
+    }
+
+    let suffix_start = (
+        offset.0 + left.len() - suffix,
+        offset.1 + right.len() - suffix,
+    );
+    operations.extend(
+        (0..suffix)
+            .map(|index| LineOperation::Equal(suffix_start.0 + index, suffix_start.1 + index)),
+    );
+}
+
+// runs the search from both corners at once until the paths meet, and returns the start
+// and end of the snake they met on. Only the furthest x on each diagonal is kept, for
+// the current D. Both sides have to be non empty and differ in their first and last line
+fn find_middle_snake(left: &[&str], right: &[&str]) -> ((usize, usize), (usize, usize)) {
+    let left_length = left.len() as isize;
+    let right_length = right.len() as isize;
+    // diagonal k = x - y in the forward search is diagonal delta - k in the backward one
+    let delta = left_length - right_length;
+    let is_delta_odd = delta % 2 != 0;
+    let max_distance = (left_length + right_length + 1) / 2;
+
+    // indexed by diagonal, shifted so that it never goes negative
+    let offset = max_distance + 1;
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+
+    for distance in 0..=max_distance {
+        let mut diagonal = -distance;
+        while diagonal <= distance {
+            let index = (diagonal + offset) as usize;
+            let mut x = if diagonal == -distance
+                || (diagonal != distance && forward[index - 1] < forward[index + 1])
+            {
+                forward[index + 1]
+            } else {
+                forward[index - 1] + 1
+            };
+            let mut y = x - diagonal;
+            let start = (x, y);
+            while x < left_length && y < right_length && left[x as usize] == right[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index] = x;
+
+            let backward_diagonal = delta - diagonal;
+            if is_delta_odd
+                && backward_diagonal.abs() < distance
+                && x + backward[(backward_diagonal + offset) as usize] >= left_length
+            {
+                return (
+                    (start.0 as usize, start.1 as usize),
+                    (x as usize, y as usize),
+                );
+            }
+            diagonal += 2;
+        }
+
+        // the same going backwards, x and y count from the end
+        let mut diagonal = -distance;
+        while diagonal <= distance {
+            let index = (diagonal + offset) as usize;
+            let mut x = if diagonal == -distance
+                || (diagonal != distance && backward[index - 1] < backward[index + 1])
+            {
+                backward[index + 1]
+            } else {
+                backward[index - 1] + 1
+            };
+            let mut y = x - diagonal;
+            let start = (x, y);
+            while x < left_length
+                && y < right_length
+                && left[(left_length - x - 1) as usize] == right[(right_length - y - 1) as usize]
+            {
+                x += 1;
+                y += 1;
+            }
+            backward[index] = x;
+
+            let forward_diagonal = delta - diagonal;
+            if !is_delta_odd
+                && forward_diagonal.abs() <= distance
+                && x + forward[(forward_diagonal + offset) as usize] >= left_length
+            {
+                return (
+                    ((left_length - x) as usize, (right_length - y) as usize),
+                    (
+                        (left_length - start.0) as usize,
+                        (right_length - start.1) as usize,
+                    ),
+                );
+            }
+            diagonal += 2;
+        }
+    }
+
+    unreachable!("the forward and backward searches always meet")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rebuilds both sides from the operations, every line has to be used once and in order
+    fn check_operations(left: &[&str], right: &[&str], operations: &[LineOperation]) {
+        let (mut next_left, mut next_right) = (0, 0);
+        for operation in operations {
+            match *operation {
+                LineOperation::Equal(left_index, right_index) => {
+                    assert_eq!((left_index, right_index), (next_left, next_right));
+                    assert_eq!(left[left_index], right[right_index]);
+                    next_left += 1;
+                    next_right += 1;
+                }
+                LineOperation::Delete(left_index) => {
+                    assert_eq!(left_index, next_left);
+                    next_left += 1;
+                }
+                LineOperation::Insert(right_index) => {
+                    assert_eq!(right_index, next_right);
+                    next_right += 1;
+                }
+            }
+        }
+        assert_eq!((next_left, next_right), (left.len(), right.len()));
+    }
+
+    fn count_edits(operations: &[LineOperation]) -> usize {
+        operations
+            .iter()
+            .filter(|operation| !matches!(operation, LineOperation::Equal(_, _)))
+            .count()
+    }
+
+    #[test]
+    fn same_lines_are_all_equal() {
+        let lines = ["{", "  \"a\": 1", "}"];
+        assert_eq!(
+            diff_lines(&lines, &lines),
+            vec![
+                LineOperation::Equal(0, 0),
+                LineOperation::Equal(1, 1),
+                LineOperation::Equal(2, 2),
+            ]
+        );
+        assert!(diff_lines(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn finds_what_was_added_and_removed() {
+        let left = ["a", "b", "c", "d"];
+        let right = ["a", "c", "x", "d"];
+        assert_eq!(
+            diff_lines(&left, &right),
+            vec![
+                LineOperation::Equal(0, 0),
+                LineOperation::Delete(1),
+                LineOperation::Equal(2, 1),
+                LineOperation::Insert(2),
+                LineOperation::Equal(3, 3),
+            ]
+        );
+
+        assert_eq!(
+            diff_lines(&[], &["a", "b"]),
+            vec![LineOperation::Insert(0), LineOperation::Insert(1)]
+        );
+        assert_eq!(diff_lines(&["a"], &[]), vec![LineOperation::Delete(0)]);
+    }
+
+    #[test]
+    fn takes_the_fewest_edits() {
+        // the example from the paper, the shortest edit script is 5 long
+        let left = ["a", "b", "c", "a", "b", "b", "a"];
+        let right = ["c", "b", "a", "b", "a", "c"];
+        let operations = diff_lines(&left, &right);
+        check_operations(&left, &right, &operations);
+        assert_eq!(count_edits(&operations), 5);
+
+        // 1 2 3 4 z in common, x and y go, w and v come in
+        let left = ["x", "1", "2", "3", "y", "4", "z"];
+        let right = ["1", "2", "w", "3", "4", "z", "v"];
+        let operations = diff_lines(&left, &right);
+        check_operations(&left, &right, &operations);
+        assert_eq!(count_edits(&operations), 4);
+    }
+
+    #[test]
+    fn splitting_at_the_middle_snake_stays_minimal() {
+        // few distinct lines, so there's plenty of equal ones to line up in different ways
+        let mut seed: u64 = 7;
+        let mut next_line = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ["a", "b", "c", "d"][(seed >> 33) as usize % 4]
+        };
+
+        for length in [1, 2, 5, 13, 40, 41] {
+            let left: Vec<&str> = (0..length).map(|_| next_line()).collect();
+            let right: Vec<&str> = (0..length * 3 / 2).map(|_| next_line()).collect();
+            let operations = diff_lines(&left, &right);
+            check_operations(&left, &right, &operations);
+
+            let mut common = vec![vec![0; right.len() + 1]; left.len() + 1];
+            for x in (0..left.len()).rev() {
+                for y in (0..right.len()).rev() {
+                    common[x][y] = if left[x] == right[y] {
+                        common[x + 1][y + 1] + 1
+                    } else {
+                        common[x + 1][y].max(common[x][y + 1])
+                    };
+                }
+            }
+            assert_eq!(
+                count_edits(&operations),
+                left.len() + right.len() - 2 * common[0][0]
+            );
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::value::{JsonNode, JsonValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    // `2`, `4` or `tabs`, same as what `:format` takes
+    pub fn parse(text: &str) -> Option<Indent> {
+        match text.trim() {
+            "tab" | "tabs" => Some(Indent::Tabs),
+            text => text
+                .parse::<usize>()
+                .ok()
+                .filter(|width| (1..=8).contains(width))
+                .map(Indent::Spaces),
+        }
+    }
+
+    fn get_unit(&self) -> String {
+        match self {
+            Indent::Spaces(width) => " ".repeat(*width),
+            Indent::Tabs => "\t".into(),
+        }
+    }
+}
+
+pub fn escape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\u{8}' => result.push_str("\\b"),
+            '\u{c}' => result.push_str("\\f"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+pub fn to_compact_string(node: &JsonNode) -> String {
+    let mut result = String::new();
+    write_compact(node, &mut result);
+    result
+}
+
+fn write_compact(node: &JsonNode, result: &mut String) {
+    match &node.value {
+        JsonValue::Null => result.push_str("null"),
+        JsonValue::Bool(value) => result.push_str(if *value { "true" } else { "false" }),
+        JsonValue::Number(value) => result.push_str(value),
+        JsonValue::String(value) => result.push_str(&escape_string(value)),
+        JsonValue::Array(items) => {
+            result.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    result.push(',');
+                }
+                write_compact(item, result);
+            }
+            result.push(']');
+        }
+        JsonValue::Object(members) => {
+            result.push('{');
+            for (index, (key, member)) in members.iter().enumerate() {
+                if index > 0 {
+                    result.push(',');
+                }
+                result.push_str(&escape_string(key));
+                result.push(':');
+                write_compact(member, result);
+            }
+            result.push('}');
+        }
+    }
+}
+
+// the start of to_compact_string, cut at `max_length` chars with a `…` when there was
+// more. Stops writing once it has enough, so previews of big subtrees stay cheap
+pub fn to_preview_string(node: &JsonNode, max_length: usize) -> String {
+    let mut result = String::new();
+    // chars are at most 4 bytes, past that there are enough of them for sure
+    write_preview(node, max_length * 4, &mut result);
+    if let Some((index, _)) = result.char_indices().nth(max_length) {
+        result.truncate(index);
+        result.push('…');
+    }
+    result
+}
+
+fn write_preview(node: &JsonNode, limit: usize, result: &mut String) {
+    match &node.value {
+        JsonValue::String(value) => {
+            let end = value
+                .char_indices()
+                .nth(limit)
+                .map_or(value.len(), |(index, _)| index);
+            result.push_str(&escape_string(&value[..end]));
+        }
+        JsonValue::Array(items) => {
+            result.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if result.len() > limit {
+                    return;
+                }
+                if index > 0 {
+                    result.push(',');
+                }
+                write_preview(item, limit, result);
+            }
+            result.push(']');
+        }
+        JsonValue::Object(members) => {
+            result.push('{');
+            for (index, (key, member)) in members.iter().enumerate() {
+                if result.len() > limit {
+                    return;
+                }
+                if index > 0 {
+                    result.push(',');
+                }
+                result.push_str(&escape_string(key));
+                result.push(':');
+                write_preview(member, limit, result);
+            }
+            result.push('}');
+        }
+        _ => write_compact(node, result),
+    }
+}
+
+pub fn to_pretty_string(node: &JsonNode, indent: Indent) -> String {
+    let mut result = String::new();
+    write_pretty(node, &indent.get_unit(), 0, &mut result);
+    result
+}
+
+fn write_pretty(node: &JsonNode, unit: &str, depth: usize, result: &mut String) {
+    match &node.value {
+        JsonValue::Array(items) if !items.is_empty() => {
+            result.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                result.push_str(&unit.repeat(depth + 1));
+                write_pretty(item, unit, depth + 1, result);
+                if index + 1 < items.len() {
+                    result.push(',');
+                }
+                result.push('\n');
+            }
+            result.push_str(&unit.repeat(depth));
+            result.push(']');
+        }
+        JsonValue::Object(members) if !members.is_empty() => {
+            result.push_str("{\n");
+            for (index, (key, member)) in members.iter().enumerate() {
+                result.push_str(&unit.repeat(depth + 1));
+                result.push_str(&escape_string(key));
+                result.push_str(": ");
+                write_pretty(member, unit, depth + 1, result);
+                if index + 1 < members.len() {
+                    result.push(',');
+                }
+                result.push('\n');
+            }
+            result.push_str(&unit.repeat(depth));
+            result.push('}');
+        }
+        // scalars and empty containers look the same either way
+        _ => write_compact(node, result),
+    }
+}
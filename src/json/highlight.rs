@@ -0,0 +1,220 @@
+use super::tokenizer::{tokenize_continued_line, TokenKind};
+
+// how many lines after an unterminated string get looked at for its closing quote
+pub const MAX_STRING_LINES: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Punctuation,
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub kind: HighlightKind,
+    // char indexes, end is exclusive
+    pub start_column: usize,
+    pub end_column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Container {
+    is_object: bool,
+    is_expecting_key: bool,
+}
+
+// where the highlighter stands at the start of a line. Telling keys from string values
+// needs everything above the line: while typing a key there's no colon yet, so we
+// track which containers we're in instead
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HighlightState {
+    containers: Vec<Container>,
+    // a string an earlier line left open, and whether it's a key or a value
+    open_string: Option<HighlightKind>,
+}
+
+// returns the spans of `line` and moves `state` on to the start of the next one.
+// `following_lines` is only read when the line ends inside a string, to see if it goes on
+pub fn highlight_line<I>(
+    line: &str,
+    state: &mut HighlightState,
+    following_lines: I,
+) -> Vec<HighlightSpan>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let open_string = state.open_string.take();
+    let tokens = tokenize_continued_line(line, 0, open_string.is_some());
+    let mut spans = Vec::with_capacity(tokens.len());
+
+    for (index, token) in tokens.iter().enumerate() {
+        let kind = match token.kind {
+            TokenKind::LeftBrace | TokenKind::LeftBracket => {
+                state.containers.push(Container {
+                    is_object: token.kind == TokenKind::LeftBrace,
+                    is_expecting_key: token.kind == TokenKind::LeftBrace,
+                });
+                HighlightKind::Punctuation
+            }
+            TokenKind::RightBrace | TokenKind::RightBracket => {
+                state.containers.pop();
+                HighlightKind::Punctuation
+            }
+            TokenKind::Colon => {
+                if let Some(container) = state.containers.last_mut() {
+                    container.is_expecting_key = false;
+                }
+                HighlightKind::Punctuation
+            }
+            TokenKind::Comma => {
+                if let Some(container) = state.containers.last_mut() {
+                    container.is_expecting_key = container.is_object;
+                }
+                HighlightKind::Punctuation
+            }
+            // the rest of a string that started on an earlier line
+            TokenKind::String | TokenKind::UnterminatedString
+                if index == 0 && open_string.is_some() =>
+            {
+                open_string.unwrap_or(HighlightKind::String)
+            }
+            TokenKind::String | TokenKind::UnterminatedString => {
+                let is_followed_by_colon = tokens
+                    .get(index + 1)
+                    .is_some_and(|next| next.kind == TokenKind::Colon);
+                let is_expecting_key = state
+                    .containers
+                    .last()
+                    .is_some_and(|container| container.is_expecting_key);
+
+                if is_followed_by_colon || is_expecting_key {
+                    if let Some(container) = state.containers.last_mut() {
+                        container.is_expecting_key = false;
+                    }
+                    HighlightKind::Key
+                } else {
+                    HighlightKind::String
+                }
+            }
+            TokenKind::Number => HighlightKind::Number,
+            TokenKind::True | TokenKind::False => HighlightKind::Boolean,
+            TokenKind::Null => HighlightKind::Null,
+            TokenKind::Invalid => HighlightKind::Invalid,
+        };
+
+        spans.push(HighlightSpan {
+            kind,
+            start_column: token.start_column,
+            end_column: token.end_column,
+        });
+    }
+
+    let is_inside_string = tokens
+        .last()
+        .is_some_and(|token| token.kind == TokenKind::UnterminatedString);
+    if is_inside_string && is_string_continued(following_lines) {
+        state.open_string = spans.last().map(|span| span.kind);
+    }
+
+    spans
+}
+
+// JSON has no multi-line strings, but pasted logs do. A line ending inside a string is
+// usually just a string still being typed though, so it only goes on when the next line
+// that has quotes at all has an odd number of them, one of them being the closing one
+fn is_string_continued<I>(following_lines: I) -> bool
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    following_lines
+        .into_iter()
+        .take(MAX_STRING_LINES)
+        .map(|line| count_quotes(line.as_ref()))
+        .find(|count| *count > 0)
+        .is_some_and(|count| count % 2 == 1)
+}
+
+fn count_quotes(line: &str) -> usize {
+    let mut count = 0;
+    let mut chars = line.chars();
+    while let Some(current) = chars.next() {
+        match current {
+            '\\' => {
+                chars.next();
+            }
+            '"' => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight_text(text: &str) -> Vec<Vec<HighlightKind>> {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let mut state = HighlightState::default();
+        lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                highlight_line(line, &mut state, &lines[index + 1..])
+                    .into_iter()
+                    .map(|span| span.kind)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tells_keys_from_values_across_lines() {
+        let kinds = highlight_text("{\n  \"a\"\n  : \"b\",\n  \"c\": [\"d\"]\n}");
+        assert_eq!(kinds[1], vec![HighlightKind::Key]);
+        assert_eq!(
+            kinds[2],
+            vec![
+                HighlightKind::Punctuation,
+                HighlightKind::String,
+                HighlightKind::Punctuation
+            ]
+        );
+        assert_eq!(kinds[3][0], HighlightKind::Key);
+        assert_eq!(kinds[3][3], HighlightKind::String);
+    }
+
+    #[test]
+    fn strings_go_on_over_line_breaks() {
+        let kinds = highlight_text("{\n  \"a\": \"first\nsecond\nthird\",\n  \"b\": 1\n}");
+        assert_eq!(kinds[1].last(), Some(&HighlightKind::String));
+        assert_eq!(kinds[2], vec![HighlightKind::String]);
+        assert_eq!(
+            kinds[3],
+            vec![HighlightKind::String, HighlightKind::Punctuation]
+        );
+        assert_eq!(kinds[4][0], HighlightKind::Key);
+        assert_eq!(kinds[4][2], HighlightKind::Number);
+    }
+
+    #[test]
+    fn a_string_being_typed_ends_with_its_line() {
+        let kinds = highlight_text("{\n  \"na\n  \"b\": true\n}");
+        assert_eq!(kinds[1], vec![HighlightKind::Key]);
+        assert_eq!(
+            kinds[2],
+            vec![
+                HighlightKind::Key,
+                HighlightKind::Punctuation,
+                HighlightKind::Boolean
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod format;
+pub mod highlight;
+pub mod parser;
+pub mod query;
+pub mod structure;
+pub mod tokenizer;
+pub mod value;
@@ -0,0 +1,419 @@
+use std::fmt::Display;
+
+use super::{
+    tokenizer::{tokenize, Token, TokenKind},
+    value::{JsonNode, JsonValue},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    // both are 0 based, same as the editor cursor
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message,
+            self.line + 1,
+            self.column + 1
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(text: &str) -> Result<JsonNode, ParseError> {
+    let tokens = tokenize(text);
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        end_of_input: end_of_input_position(text),
+    };
+
+    let node = parser.parse_value()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error_at(
+            token,
+            format!("Expected end of input but found '{}'", token.text),
+        ));
+    }
+
+    Ok(node)
+}
+
+fn end_of_input_position(text: &str) -> (usize, usize) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let last_line = lines.len() - 1;
+    (last_line, lines[last_line].chars().count())
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    end_of_input: (usize, usize),
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<&'a Token, ParseError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => {
+                Err(self.error_at_end(format!("Expected {} but reached end of input", expected)))
+            }
+        }
+    }
+
+    fn error_at(&self, token: &Token, message: String) -> ParseError {
+        ParseError {
+            message,
+            line: token.line,
+            column: token.start_column,
+        }
+    }
+
+    fn error_at_end(&self, message: String) -> ParseError {
+        ParseError {
+            message,
+            line: self.end_of_input.0,
+            column: self.end_of_input.1,
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonNode, ParseError> {
+        let token = self.next("a value")?;
+        let value = match token.kind {
+            TokenKind::LeftBrace => return self.parse_object(token),
+            TokenKind::LeftBracket => return self.parse_array(token),
+            TokenKind::String => JsonValue::String(self.parse_string(token)?),
+            TokenKind::Number => JsonValue::Number(self.parse_number(token)?),
+            TokenKind::True => JsonValue::Bool(true),
+            TokenKind::False => JsonValue::Bool(false),
+            TokenKind::Null => JsonValue::Null,
+            TokenKind::UnterminatedString => {
+                return Err(self.error_at(token, "Unterminated string".into()))
+            }
+            _ => {
+                return Err(self.error_at(
+                    token,
+                    format!("Expected a value but found '{}'", token.text),
+                ))
+            }
+        };
+
+        Ok(JsonNode::new(value, token.line, token.line))
+    }
+
+    fn parse_object(&mut self, opening: &Token) -> Result<JsonNode, ParseError> {
+        let mut members: Vec<(String, JsonNode)> = Vec::new();
+
+        if let Some(token) = self.peek() {
+            if token.kind == TokenKind::RightBrace {
+                self.position += 1;
+                return Ok(JsonNode::new(
+                    JsonValue::Object(members),
+                    opening.line,
+                    token.line,
+                ));
+            }
+        }
+
+        loop {
+            let key_token = self.next("a key")?;
+            let key = match key_token.kind {
+                TokenKind::String => self.parse_string(key_token)?,
+                TokenKind::UnterminatedString => {
+                    return Err(self.error_at(key_token, "Unterminated string".into()))
+                }
+                _ => {
+                    return Err(self.error_at(
+                        key_token,
+                        format!("Expected a key but found '{}'", key_token.text),
+                    ))
+                }
+            };
+
+            let colon = self.next("':'")?;
+            if colon.kind != TokenKind::Colon {
+                return Err(
+                    self.error_at(colon, format!("Expected ':' but found '{}'", colon.text))
+                );
+            }
+
+            let mut node = self.parse_value()?;
+            node.start_line = key_token.line;
+            members.push((key, node));
+
+            let separator = self.next("',' or '}'")?;
+            match separator.kind {
+                TokenKind::Comma => continue,
+                TokenKind::RightBrace => {
+                    return Ok(JsonNode::new(
+                        JsonValue::Object(members),
+                        opening.line,
+                        separator.line,
+                    ))
+                }
+                _ => {
+                    return Err(self.error_at(
+                        separator,
+                        format!("Expected ',' or '}}' but found '{}'", separator.text),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self, opening: &Token) -> Result<JsonNode, ParseError> {
+        let mut items: Vec<JsonNode> = Vec::new();
+
+        if let Some(token) = self.peek() {
+            if token.kind == TokenKind::RightBracket {
+                self.position += 1;
+                return Ok(JsonNode::new(
+                    JsonValue::Array(items),
+                    opening.line,
+                    token.line,
+                ));
+            }
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+
+            let separator = self.next("',' or ']'")?;
+            match separator.kind {
+                TokenKind::Comma => continue,
+                TokenKind::RightBracket => {
+                    return Ok(JsonNode::new(
+                        JsonValue::Array(items),
+                        opening.line,
+                        separator.line,
+                    ))
+                }
+                _ => {
+                    return Err(self.error_at(
+                        separator,
+                        format!("Expected ',' or ']' but found '{}'", separator.text),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parse_number(&self, token: &Token) -> Result<String, ParseError> {
+        if is_valid_number(&token.text) {
+            Ok(token.text.clone())
+        } else {
+            Err(self.error_at(token, format!("Invalid number '{}'", token.text)))
+        }
+    }
+
+    fn parse_string(&self, token: &Token) -> Result<String, ParseError> {
+        // the tokenizer guarantees the surrounding quotes
+        let content: Vec<char> = token.text.chars().collect();
+        let content = &content[1..content.len() - 1];
+        let mut result = String::with_capacity(content.len());
+        let mut index = 0;
+
+        while index < content.len() {
+            let current = content[index];
+            if current != '\\' {
+                if current.is_control() {
+                    return Err(ParseError {
+                        message: "Control character in string".into(),
+                        line: token.line,
+                        column: token.start_column + index + 1,
+                    });
+                }
+                result.push(current);
+                index += 1;
+                continue;
+            }
+
+            let escape_start = index;
+            let escape_error = |message: &str| ParseError {
+                message: message.into(),
+                line: token.line,
+                column: token.start_column + escape_start + 1,
+            };
+
+            let escaped = *content
+                .get(index + 1)
+                .ok_or_else(|| escape_error("Invalid escape sequence"))?;
+            index += 2;
+            match escaped {
+                '"' => result.push('"'),
+                '\\' => result.push('\\'),
+                '/' => result.push('/'),
+                'b' => result.push('\u{8}'),
+                'f' => result.push('\u{c}'),
+                'n' => result.push('\n'),
+                'r' => result.push('\r'),
+                't' => result.push('\t'),
+                'u' => {
+                    let high = read_hex(content, index)
+                        .ok_or_else(|| escape_error("Invalid unicode escape"))?;
+                    index += 4;
+
+                    let code_point = if (0xD800..0xDC00).contains(&high) {
+                        let has_low = content.get(index) == Some(&'\\')
+                            && content.get(index + 1) == Some(&'u');
+                        let low = if has_low {
+                            read_hex(content, index + 2)
+                        } else {
+                            None
+                        }
+                        .filter(|low| (0xDC00..0xE000).contains(low))
+                        .ok_or_else(|| escape_error("Invalid surrogate pair"))?;
+                        index += 6;
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        high
+                    };
+
+                    result.push(
+                        char::from_u32(code_point)
+                            .ok_or_else(|| escape_error("Invalid unicode escape"))?,
+                    );
+                }
+                _ => return Err(escape_error("Invalid escape sequence")),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn read_hex(content: &[char], start: usize) -> Option<u32> {
+    let digits: String = content.get(start..start + 4)?.iter().collect();
+    u32::from_str_radix(&digits, 16).ok()
+}
+
+// -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+pub fn is_valid_number(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    let count_digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+
+    if bytes.get(index) == Some(&b'-') {
+        index += 1;
+    }
+
+    match count_digits(index) {
+        0 => return false,
+        digits => {
+            if digits > 1 && bytes[index] == b'0' {
+                return false;
+            }
+            index += digits;
+        }
+    }
+
+    if bytes.get(index) == Some(&b'.') {
+        index += 1;
+        match count_digits(index) {
+            0 => return false,
+            digits => index += digits,
+        }
+    }
+
+    if matches!(bytes.get(index), Some(b'e') | Some(b'E')) {
+        index += 1;
+        if matches!(bytes.get(index), Some(b'+') | Some(b'-')) {
+            index += 1;
+        }
+        match count_digits(index) {
+            0 => return false,
+            digits => index += digits,
+        }
+    }
+
+    index == bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_members_in_order_with_their_lines() {
+        let document =
+            parse("{\n  \"b\": [1, 2.50],\n  \"a\": {\n    \"c\": null\n  }\n}").unwrap();
+        let JsonValue::Object(members) = &document.value else {
+            panic!("expected an object");
+        };
+
+        let keys: Vec<&str> = members.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["b", "a"]);
+        assert_eq!((document.start_line, document.end_line), (0, 5));
+        assert_eq!((members[1].1.start_line, members[1].1.end_line), (2, 4));
+        assert_eq!(
+            members[0].1.value,
+            JsonValue::Array(vec![
+                JsonNode::new(JsonValue::Number("1".into()), 1, 1),
+                JsonNode::new(JsonValue::Number("2.50".into()), 1, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn unescapes_strings() {
+        let document = parse(r#""a\"b\\c\n\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(document.value, JsonValue::String("a\"b\\c\né😀".into()));
+    }
+
+    #[test]
+    fn points_at_what_went_wrong() {
+        let error = parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!(error.message, "Expected ':' but found '2'");
+        assert_eq!((error.line, error.column), (2, 6));
+
+        let error = parse("[1, 2").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected ',' or ']' but reached end of input"
+        );
+        assert_eq!((error.line, error.column), (0, 5));
+
+        assert_eq!(parse("01").unwrap_err().message, "Invalid number '01'");
+        assert_eq!(
+            parse("\"\\x\"").unwrap_err().message,
+            "Invalid escape sequence"
+        );
+        assert_eq!(
+            parse("\"\\ud83d\"").unwrap_err().message,
+            "Invalid surrogate pair"
+        );
+        assert_eq!(
+            parse("{} []").unwrap_err().message,
+            "Expected end of input but found '['"
+        );
+    }
+
+    #[test]
+    fn checks_numbers_like_the_grammar_does() {
+        for valid in ["0", "-0", "12", "1.5", "-1.5e10", "1E+2", "2e-3"] {
+            assert!(is_valid_number(valid), "{}", valid);
+        }
+        for invalid in ["", "-", "01", "1.", ".5", "1e", "1e+", "+1", "1.5.2"] {
+            assert!(!is_valid_number(invalid), "{}", invalid);
+        }
+    }
+}
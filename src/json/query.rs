@@ -0,0 +1,383 @@
+use std::fmt::Display;
+
+use super::value::{JsonNode, JsonValue};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuerySegment {
+    Key(String),
+    // negative ones count from the end, `[-1]` is the last item
+    Index(isize),
+    // `[start:end]`, the end isn't included and both can be left out
+    Slice(Option<isize>, Option<isize>),
+    Wildcard,
+    // `..`, the node and everything below it, the next segment picks from all of them
+    Descendants,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+// a node the query picked, with the path that leads to it
+#[derive(Debug, Clone)]
+pub struct QueryMatch<'a> {
+    pub path: String,
+    pub node: &'a JsonNode,
+}
+
+// a JSONPath subset: `$.a.b`, `$['a b']`, `$.items[3]`, `$.items[-1]`, `$.items[1:3]`,
+// `$.items[*].id` and `$..id`. jq style paths like `.items[].id` work too
+pub fn parse_query(expression: &str) -> Result<Vec<QuerySegment>, QueryError> {
+    let error = |message: &str| QueryError {
+        message: format!("{} in path '{}'", message, expression),
+    };
+
+    let chars: Vec<char> = expression.trim().chars().collect();
+    let mut index = match chars.first() {
+        Some('$') => 1,
+        Some('.') => 0,
+        _ => return Err(error("Expected '$' or '.' at the start")),
+    };
+
+    let mut segments = Vec::new();
+    while index < chars.len() {
+        match chars[index] {
+            '.' => {
+                if chars.get(index + 1) == Some(&'.') {
+                    segments.push(QuerySegment::Descendants);
+                    index += 1;
+                }
+
+                let start = index + 1;
+                let mut end = start;
+                while end < chars.len() && !matches!(chars[end], '.' | '[') {
+                    end += 1;
+                }
+                let key: String = chars[start..end].iter().collect();
+                match key.as_str() {
+                    "*" => segments.push(QuerySegment::Wildcard),
+                    // `$..[0]` and jq's `.[0]`
+                    "" if chars.get(end) == Some(&'[') => {}
+                    // jq's `.`, the whole document
+                    "" if chars.len() == 1 => {}
+                    "" => return Err(error("Expected a key after '.'")),
+                    _ => segments.push(QuerySegment::Key(key)),
+                }
+                index = end;
+            }
+            '[' => {
+                let end = chars[index..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .map(|offset| index + offset)
+                    .ok_or_else(|| error("Missing ']'"))?;
+                let content: String = chars[index + 1..end].iter().collect();
+                segments.push(
+                    parse_bracket(content.trim())
+                        .ok_or_else(|| error("Expected an index, a slice, '*' or a quoted key"))?,
+                );
+                index = end + 1;
+            }
+            c => return Err(error(&format!("Unexpected '{}'", c))),
+        }
+    }
+
+    Ok(segments)
+}
+
+fn parse_bracket(content: &str) -> Option<QuerySegment> {
+    let is_quoted = content.len() >= 2
+        && ((content.starts_with('\'') && content.ends_with('\''))
+            || (content.starts_with('"') && content.ends_with('"')));
+    // jq writes `[]` for every item
+    if content.is_empty() || content == "*" {
+        return Some(QuerySegment::Wildcard);
+    }
+    if is_quoted {
+        return Some(QuerySegment::Key(content[1..content.len() - 1].to_string()));
+    }
+    if let Some((start, end)) = content.split_once(':') {
+        let parse_bound = |bound: &str| match bound.trim() {
+            "" => Some(None),
+            bound => bound.parse().ok().map(Some),
+        };
+        return Some(QuerySegment::Slice(parse_bound(start)?, parse_bound(end)?));
+    }
+    content.parse().ok().map(QuerySegment::Index)
+}
+
+// every node the query picks, in document order
+pub fn evaluate_query<'a>(
+    document: &'a JsonNode,
+    segments: &[QuerySegment],
+) -> Vec<QueryMatch<'a>> {
+    let mut matches = Vec::from([QueryMatch {
+        path: "$".into(),
+        node: document,
+    }]);
+
+    for segment in segments {
+        matches = matches
+            .into_iter()
+            .flat_map(|query_match| apply_segment(segment, query_match))
+            .collect();
+    }
+    // `..` goes through parents before their children, whatever comes after it can
+    // pick nodes out of order
+    matches.sort_by_key(|query_match| query_match.node.start_line);
+    matches
+}
+
+fn apply_segment<'a>(segment: &QuerySegment, query_match: QueryMatch<'a>) -> Vec<QueryMatch<'a>> {
+    let QueryMatch { path, node } = query_match;
+
+    match (segment, &node.value) {
+        (QuerySegment::Key(key), JsonValue::Object(_)) => node
+            .get_member(key)
+            .map(|member| QueryMatch {
+                path: push_path_key(&path, key),
+                node: member,
+            })
+            .into_iter()
+            .collect(),
+        (QuerySegment::Index(index), JsonValue::Array(items)) => {
+            let index = if *index < 0 {
+                items.len() as isize + index
+            } else {
+                *index
+            };
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| Some((index, items.get(index)?)))
+                .map(|(index, item)| QueryMatch {
+                    path: format!("{}[{}]", path, index),
+                    node: item,
+                })
+                .into_iter()
+                .collect()
+        }
+        (QuerySegment::Slice(start, end), JsonValue::Array(items)) => {
+            let length = items.len() as isize;
+            let clamp = |bound: isize| {
+                let bound = if bound < 0 { length + bound } else { bound };
+                bound.clamp(0, length) as usize
+            };
+            let start = start.map_or(0, clamp);
+            let end = end.map_or(items.len(), clamp);
+            items
+                .iter()
+                .enumerate()
+                .take(end)
+                .skip(start)
+                .map(|(index, item)| QueryMatch {
+                    path: format!("{}[{}]", path, index),
+                    node: item,
+                })
+                .collect()
+        }
+        (QuerySegment::Wildcard, _) => get_children(&path, node),
+        (QuerySegment::Descendants, _) => {
+            let children = get_children(&path, node);
+            let mut descendants = Vec::from([QueryMatch { path, node }]);
+            for child in children {
+                descendants.extend(apply_segment(segment, child));
+            }
+            descendants
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn get_children<'a>(path: &str, node: &'a JsonNode) -> Vec<QueryMatch<'a>> {
+    match &node.value {
+        JsonValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| QueryMatch {
+                path: format!("{}[{}]", path, index),
+                node: item,
+            })
+            .collect(),
+        JsonValue::Object(members) => members
+            .iter()
+            .map(|(key, member)| QueryMatch {
+                path: push_path_key(path, key),
+                node: member,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// the path of the innermost node on `line`. Without columns there's no telling which
+// member of a line like `{"id": 1, "name": "a"}` is meant, so it stops at the node
+// holding them
+pub fn find_path_at_line(document: &JsonNode, line: usize) -> Option<String> {
+    find_nodes_at_line(document, line)
+        .pop()
+        .map(|query_match| query_match.path)
+}
+
+// the nodes on the way down to that innermost one, the root first
+pub fn find_nodes_at_line(document: &JsonNode, line: usize) -> Vec<QueryMatch<'_>> {
+    if line < document.start_line || document.end_line < line {
+        return Vec::new();
+    }
+
+    let mut nodes = Vec::from([QueryMatch {
+        path: "$".into(),
+        node: document,
+    }]);
+    loop {
+        let QueryMatch { path, node } = &nodes[nodes.len() - 1];
+        let child =
+            match &node.value {
+                JsonValue::Array(items) => find_only_child_at_line(items.iter().enumerate(), line)
+                    .map(|(index, item)| QueryMatch {
+                        path: format!("{}[{}]", path, index),
+                        node: item,
+                    }),
+                JsonValue::Object(members) => find_only_child_at_line(
+                    members.iter().map(|(key, member)| (key.as_str(), member)),
+                    line,
+                )
+                .map(|(key, member)| QueryMatch {
+                    path: push_path_key(path, key),
+                    node: member,
+                }),
+                _ => None,
+            };
+        match child {
+            Some(child) => nodes.push(child),
+            None => break,
+        }
+    }
+
+    nodes
+}
+
+// children come in document order, so the ones starting below `line` can be skipped
+fn find_only_child_at_line<'a, T>(
+    children: impl Iterator<Item = (T, &'a JsonNode)>,
+    line: usize,
+) -> Option<(T, &'a JsonNode)> {
+    let mut children = children
+        .take_while(|(_, child)| child.start_line <= line)
+        .filter(|(_, child)| line <= child.end_line);
+    match (children.next(), children.next()) {
+        (Some(child), None) => Some(child),
+        _ => None,
+    }
+}
+
+// `.key` when it reads fine like that, `['some key']` otherwise
+pub fn push_path_key(path: &str, key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    if is_identifier {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}['{}']", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parser::parse;
+
+    fn query(text: &str, expression: &str) -> Vec<String> {
+        let document = parse(text).unwrap();
+        evaluate_query(&document, &parse_query(expression).unwrap())
+            .into_iter()
+            .map(|query_match| query_match.path)
+            .collect()
+    }
+
+    #[test]
+    fn parses_jsonpath_and_jq_paths() {
+        assert_eq!(
+            parse_query("$.a['b c'][-1][1:][*]..id").unwrap(),
+            vec![
+                QuerySegment::Key("a".into()),
+                QuerySegment::Key("b c".into()),
+                QuerySegment::Index(-1),
+                QuerySegment::Slice(Some(1), None),
+                QuerySegment::Wildcard,
+                QuerySegment::Descendants,
+                QuerySegment::Key("id".into()),
+            ]
+        );
+        assert_eq!(
+            parse_query(".items[].id").unwrap(),
+            parse_query("$.items[*].id").unwrap()
+        );
+        assert_eq!(parse_query(".").unwrap(), vec![]);
+        assert_eq!(parse_query("$").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn rejects_broken_paths() {
+        assert_eq!(
+            parse_query("a.b").unwrap_err().message,
+            "Expected '$' or '.' at the start in path 'a.b'"
+        );
+        assert_eq!(
+            parse_query("$.a[0").unwrap_err().message,
+            "Missing ']' in path '$.a[0'"
+        );
+        assert_eq!(
+            parse_query("$.a[x]").unwrap_err().message,
+            "Expected an index, a slice, '*' or a quoted key in path '$.a[x]'"
+        );
+        assert!(parse_query("$.a.").is_err());
+        assert!(parse_query("$a").is_err());
+    }
+
+    #[test]
+    fn picks_keys_indexes_and_slices() {
+        let text = "{\"items\": [{\"id\": 1}, {\"id\": 2}, {\"id\": 3}], \"a b\": true}";
+        assert_eq!(query(text, "$.items[1].id"), vec!["$.items[1].id"]);
+        assert_eq!(query(text, "$.items[-1]"), vec!["$.items[2]"]);
+        assert_eq!(
+            query(text, "$.items[-2:]"),
+            vec!["$.items[1]", "$.items[2]"]
+        );
+        assert_eq!(query(text, "$.items[:1]"), vec!["$.items[0]"]);
+        assert_eq!(query(text, "$['a b']"), vec!["$['a b']"]);
+        assert_eq!(query(text, "$.items[5]"), Vec::<String>::new());
+        assert_eq!(query(text, "$.missing.id"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wildcards_and_descendants_come_back_in_document_order() {
+        let text = "{\n  \"id\": 0,\n  \"items\": [\n    {\"id\": 1},\n    {\"child\": {\"id\": 2}}\n  ]\n}";
+        assert_eq!(query(text, "$.items[*]"), vec!["$.items[0]", "$.items[1]"]);
+        assert_eq!(
+            query(text, "$..id"),
+            vec!["$.id", "$.items[0].id", "$.items[1].child.id"]
+        );
+    }
+
+    #[test]
+    fn finds_the_path_on_a_line() {
+        let document = parse("{\n  \"a\": [\n    1,\n    {\"b\": 2, \"c\": 3}\n  ]\n}").unwrap();
+        assert_eq!(find_path_at_line(&document, 2).as_deref(), Some("$.a[0]"));
+        assert_eq!(find_path_at_line(&document, 3).as_deref(), Some("$.a[1]"));
+        assert_eq!(find_path_at_line(&document, 0).as_deref(), Some("$"));
+        assert_eq!(find_path_at_line(&document, 9), None);
+    }
+}
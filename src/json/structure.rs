@@ -0,0 +1,88 @@
+use super::tokenizer::{tokenize, TokenKind};
+
+// an object or array, found by matching brackets so it works for documents that
+// don't parse as a whole. Containers that never got closed are left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerSpan {
+    pub is_object: bool,
+    pub start_line: usize,
+    // char index of the opening bracket
+    pub start_column: usize,
+    pub end_line: usize,
+    // char index of the closing bracket
+    pub end_column: usize,
+    // keys for objects, items for arrays
+    pub child_count: usize,
+    pub depth: usize,
+}
+
+struct OpenContainer {
+    is_object: bool,
+    start_line: usize,
+    start_column: usize,
+    comma_count: usize,
+    is_empty: bool,
+}
+
+// sorted by where they start, so outer containers come before the ones inside them
+pub fn find_containers(text: &str) -> Vec<ContainerSpan> {
+    let mut containers = Vec::new();
+    let mut open_containers: Vec<OpenContainer> = Vec::new();
+
+    for token in tokenize(text) {
+        match token.kind {
+            TokenKind::LeftBrace | TokenKind::LeftBracket => {
+                if let Some(parent) = open_containers.last_mut() {
+                    parent.is_empty = false;
+                }
+                open_containers.push(OpenContainer {
+                    is_object: token.kind == TokenKind::LeftBrace,
+                    start_line: token.line,
+                    start_column: token.start_column,
+                    comma_count: 0,
+                    is_empty: true,
+                });
+            }
+            TokenKind::RightBrace | TokenKind::RightBracket => {
+                let is_object = token.kind == TokenKind::RightBrace;
+                // a stray closing bracket doesn't close anything
+                if open_containers
+                    .last()
+                    .is_none_or(|container| container.is_object != is_object)
+                {
+                    continue;
+                }
+                let Some(container) = open_containers.pop() else {
+                    continue;
+                };
+
+                containers.push(ContainerSpan {
+                    is_object,
+                    start_line: container.start_line,
+                    start_column: container.start_column,
+                    end_line: token.line,
+                    end_column: token.start_column,
+                    child_count: if container.is_empty {
+                        0
+                    } else {
+                        container.comma_count + 1
+                    },
+                    depth: open_containers.len(),
+                });
+            }
+            TokenKind::Comma => {
+                if let Some(container) = open_containers.last_mut() {
+                    container.comma_count += 1;
+                }
+            }
+            _ => {
+                if let Some(container) = open_containers.last_mut() {
+                    container.is_empty = false;
+                }
+            }
+        }
+    }
+
+    containers.sort_by_key(|container| (container.start_line, container.start_column));
+    containers
+}
@@ -0,0 +1,158 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    String,
+    UnterminatedString,
+    Number,
+    True,
+    False,
+    Null,
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub line: usize,
+    // both columns are char indexes, end is exclusive
+    pub start_column: usize,
+    pub end_column: usize,
+}
+
+// JSON does not allow raw newlines inside of strings, so no token can ever span
+// more than one line. That lets us tokenize line by line, which is exactly how
+// the editor stores its data anyway
+pub fn tokenize(text: &str) -> Vec<Token> {
+    text.split('\n')
+        .enumerate()
+        .flat_map(|(line_index, line)| tokenize_line(line, line_index))
+        .collect()
+}
+
+pub fn tokenize_line(line: &str, line_index: usize) -> Vec<Token> {
+    tokenize_continued_line(line, line_index, false)
+}
+
+// like tokenize_line, but the line can start inside a string an earlier line left open.
+// Only the highlighter asks for that, it guesses where strings with raw line breaks end
+// even though they aren't valid JSON
+pub fn tokenize_continued_line(line: &str, line_index: usize, is_in_string: bool) -> Vec<Token> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    if is_in_string {
+        let (end, is_terminated) = scan_string(&chars, 0);
+        position = end;
+        tokens.push(Token {
+            kind: if is_terminated {
+                TokenKind::String
+            } else {
+                TokenKind::UnterminatedString
+            },
+            text: chars[..position].iter().collect(),
+            line: line_index,
+            start_column: 0,
+            end_column: position,
+        });
+    }
+
+    while position < chars.len() {
+        let current = chars[position];
+        if current.is_whitespace() {
+            position += 1;
+            continue;
+        }
+
+        let start = position;
+        let kind = match current {
+            '{' => {
+                position += 1;
+                TokenKind::LeftBrace
+            }
+            '}' => {
+                position += 1;
+                TokenKind::RightBrace
+            }
+            '[' => {
+                position += 1;
+                TokenKind::LeftBracket
+            }
+            ']' => {
+                position += 1;
+                TokenKind::RightBracket
+            }
+            ':' => {
+                position += 1;
+                TokenKind::Colon
+            }
+            ',' => {
+                position += 1;
+                TokenKind::Comma
+            }
+            '"' => {
+                let (end, is_terminated) = scan_string(&chars, position + 1);
+                position = end;
+                if is_terminated {
+                    TokenKind::String
+                } else {
+                    TokenKind::UnterminatedString
+                }
+            }
+            '-' | '0'..='9' => {
+                while position < chars.len() && is_number_char(chars[position]) {
+                    position += 1;
+                }
+                TokenKind::Number
+            }
+            c if c.is_alphabetic() => {
+                while position < chars.len() && chars[position].is_alphanumeric() {
+                    position += 1;
+                }
+                match chars[start..position].iter().collect::<String>().as_str() {
+                    "true" => TokenKind::True,
+                    "false" => TokenKind::False,
+                    "null" => TokenKind::Null,
+                    _ => TokenKind::Invalid,
+                }
+            }
+            _ => {
+                position += 1;
+                TokenKind::Invalid
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: chars[start..position].iter().collect(),
+            line: line_index,
+            start_column: start,
+            end_column: position,
+        });
+    }
+
+    tokens
+}
+
+// goes from inside a string to just past its closing quote, or to the end of the line
+fn scan_string(chars: &[char], start: usize) -> (usize, bool) {
+    let mut position = start;
+    while position < chars.len() {
+        match chars[position] {
+            '\\' => position += 2,
+            '"' => return (position + 1, true),
+            _ => position += 1,
+        }
+    }
+    (chars.len(), false)
+}
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')
+}
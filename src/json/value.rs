@@ -0,0 +1,95 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    // we keep the number exactly as it was written, so that `1.0` and `1` can
+    // be told apart when diffing
+    Number(String),
+    String(String),
+    Array(Vec<JsonNode>),
+    Object(Vec<(String, JsonNode)>),
+}
+
+// a parsed value together with the editor lines it spans. For object members
+// the span starts at the line holding the key
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonNode {
+    pub value: JsonValue,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl JsonValue {
+    pub fn get_type_name(&self) -> &'static str {
+        match self {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "boolean",
+            JsonValue::Number(_) => "number",
+            JsonValue::String(_) => "string",
+            JsonValue::Array(_) => "array",
+            JsonValue::Object(_) => "object",
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        matches!(self, JsonValue::Array(_) | JsonValue::Object(_))
+    }
+}
+
+impl JsonNode {
+    pub fn new(value: JsonValue, start_line: usize, end_line: usize) -> Self {
+        JsonNode {
+            value,
+            start_line,
+            end_line,
+        }
+    }
+
+    // same document with the members of every object sorted by key, line spans are kept
+    // as they were since they don't mean much after reordering anyway
+    pub fn to_sorted(&self) -> JsonNode {
+        let value = match &self.value {
+            JsonValue::Array(items) => {
+                JsonValue::Array(items.iter().map(JsonNode::to_sorted).collect())
+            }
+            JsonValue::Object(members) => {
+                let mut members: Vec<(String, JsonNode)> = members
+                    .iter()
+                    .map(|(key, member)| (key.clone(), member.to_sorted()))
+                    .collect();
+                members.sort_by(|left, right| left.0.cmp(&right.0));
+                JsonValue::Object(members)
+            }
+            value => value.clone(),
+        };
+
+        JsonNode::new(value, self.start_line, self.end_line)
+    }
+
+    pub fn get_member(&self, key: &str) -> Option<&JsonNode> {
+        match &self.value {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(member_key, _)| member_key == key)
+                .map(|(_, node)| node),
+            _ => None,
+        }
+    }
+}
+
+// https://datatracker.ietf.org/doc/html/rfc6901
+pub fn push_pointer_segment(pointer: &str, segment: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        segment.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+pub fn split_pointer(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
@@ -4,7 +4,9 @@ use anyhow::Result;
 use ui::manager::UiManager;
 
 pub mod database;
+pub mod diff;
 pub mod event;
+pub mod json;
 pub mod models;
 pub mod ui;
 
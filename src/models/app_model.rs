@@ -6,6 +6,8 @@ pub enum AppMode {
     Normal,
     Editing,
     Command,
+    Visual,
+    VisualLine,
 }
 
 pub enum AppModelActions {
@@ -1,7 +1,11 @@
+use crate::database::db_manager::DatabaseActions;
+
 use super::{
     app_model::{AppMode, AppModel, AppModelActions},
     command_bar::{CommandBarModel, CommandBarModelActions},
+    diff_summary::{DiffSummaryModel, DiffSummaryModelActions},
     editor::editor_container_models::{EditorContainerModel, EditorContainerModelActions},
+    popup::{PopupModel, PopupModelActions},
     router::{RouterModel, RouterModelActions},
 };
 
@@ -15,6 +19,9 @@ pub enum AppStateActions {
     CommandBarActions(CommandBarModelActions),
     RouterModelActions(RouterModelActions),
     EditorActions(EditorContainerModelActions),
+    DiffSummaryActions(DiffSummaryModelActions),
+    PopupActions(PopupModelActions),
+    DatabaseActions(DatabaseActions),
 }
 
 #[derive(Debug, Default, Clone)]
@@ -23,6 +30,10 @@ pub struct AppState {
     pub command_bar_store: CommandBarModel,
     pub router_store: RouterModel,
     pub editor_store: EditorContainerModel,
+    pub diff_summary_store: DiffSummaryModel,
+    pub popup_store: PopupModel,
+    // the database lives on the model thread, the models just queue up what they want done
+    database_queue: Vec<DatabaseActions>,
 }
 
 impl AppState {
@@ -39,10 +50,25 @@ impl AppState {
                     }
                 },
                 AppStateActions::AppModelActions(model_action) => {
-                    action_to_resolve = self.app_state_store.update(model_action)
+                    action_to_resolve = self.app_state_store.update(model_action);
+                    // like vim, an error stays up until the next command or until typing starts
+                    if self.app_state_store.get_app_mode() == AppMode::Editing {
+                        self.command_bar_store.clear_error();
+                    }
+                    // selections only mean something in visual mode, however it gets left
+                    if !matches!(
+                        self.app_state_store.get_app_mode(),
+                        AppMode::Visual | AppMode::VisualLine
+                    ) {
+                        self.editor_store
+                            .update(EditorContainerModelActions::DropSelections);
+                    }
                 }
                 AppStateActions::CommandBarActions(model_action) => {
-                    let should_reset_mode = matches!(model_action, CommandBarModelActions::Enter);
+                    let should_reset_mode = matches!(
+                        model_action,
+                        CommandBarModelActions::Enter | CommandBarModelActions::Reset
+                    );
                     action_to_resolve = self.command_bar_store.update(model_action);
 
                     if should_reset_mode {
@@ -56,6 +82,16 @@ impl AppState {
                 AppStateActions::EditorActions(model_action) => {
                     action_to_resolve = self.editor_store.update(model_action)
                 }
+                AppStateActions::DiffSummaryActions(model_action) => {
+                    action_to_resolve = self.diff_summary_store.update(model_action)
+                }
+                AppStateActions::PopupActions(model_action) => {
+                    action_to_resolve = self.popup_store.update(model_action)
+                }
+                AppStateActions::DatabaseActions(database_action) => {
+                    action_to_resolve = None;
+                    self.database_queue.push(database_action);
+                }
             }
         }
     }
@@ -63,4 +99,8 @@ impl AppState {
     pub fn new() -> Self {
         AppState::default()
     }
+
+    pub fn take_database_actions(&mut self) -> Vec<DatabaseActions> {
+        std::mem::take(&mut self.database_queue)
+    }
 }
@@ -1,11 +1,18 @@
-use crate::models::{app_model::AppModelActions, app_state::AppStateActions};
+use crate::models::{
+    app_model::{AppMode, AppModelActions},
+    app_state::AppStateActions,
+    diff_summary::DiffSummaryModelActions,
+};
 
-use super::editor::editor_container_models::EditorContainerModelActions;
+use crate::json::format::Indent;
+
+use super::editor::editor_container_models::{DiffJump, EditorContainerModelActions};
 
 #[derive(Debug, Clone)]
 pub struct CommandBarModel {
     pub input: String,
     pub cursor_position: usize,
+    error: Option<String>,
 }
 
 pub enum CursorDirection {
@@ -14,18 +21,30 @@ pub enum CursorDirection {
 }
 
 pub enum CommandBarModelActions {
+    // opens the prompt, `:` for commands, `/` and `?` for searching
+    Start(char),
     Input(char),
     MoveCursor(CursorDirection),
     Backspace,
     Enter,
     Reset,
+    // shown in the status bar until the next command, parse errors go there too
+    ShowError(String),
 }
 
 impl CommandBarModel {
     pub fn update(&mut self, action: CommandBarModelActions) -> Option<AppStateActions> {
         match action {
+            CommandBarModelActions::Start(prefix) => {
+                self.error = None;
+                self.input = prefix.to_string();
+                self.cursor_position = 1;
+                Some(AppStateActions::AppModelActions(
+                    AppModelActions::ChangeMode(AppMode::Command),
+                ))
+            }
             CommandBarModelActions::Input(data) => {
-                self.input.insert(self.cursor_position, data);
+                self.input.insert(self.get_byte_index(), data);
                 self.cursor_position += 1;
                 None
             }
@@ -37,7 +56,7 @@ impl CommandBarModel {
                     None
                 }
                 CursorDirection::Right => {
-                    if self.cursor_position < self.input.len() {
+                    if self.cursor_position < self.input.chars().count() {
                         self.cursor_position += 1;
                     }
                     None
@@ -61,14 +80,25 @@ impl CommandBarModel {
             CommandBarModelActions::Enter => {
                 let input = self.get_input();
                 let command = input.to_string().split_off(1);
+                let is_search = input.starts_with(['/', '?']);
+                let is_backward = input.starts_with('?');
 
                 self.reset_input();
+                if is_search {
+                    return Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::Search(command, is_backward),
+                    ));
+                }
                 self.handle_input(&command)
             }
             CommandBarModelActions::Reset => {
                 self.reset_input();
                 None
             }
+            CommandBarModelActions::ShowError(error) => {
+                self.error = Some(error);
+                None
+            }
         }
     }
 
@@ -85,14 +115,119 @@ impl CommandBarModel {
         self.cursor_position
     }
 
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn clear_error(&mut self) {
+        self.error = None;
+    }
+
+    // the cursor counts chars since that's what the screen shows, String wants bytes
+    fn get_byte_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .nth(self.cursor_position)
+            .map_or(self.input.len(), |(index, _)| index)
+    }
+
     pub fn handle_input(&self, command: &str) -> Option<AppStateActions> {
         match command {
             "q" | "exit" | "quit" => Some(AppStateActions::AppModelActions(AppModelActions::Exit)),
-            "save" => None,
+            "save" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Save,
+            )),
             "vsplit" => Some(AppStateActions::EditorActions(
                 EditorContainerModelActions::AddEditor,
             )),
-            _ => None,
+            "diff" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Diff,
+            )),
+            "tree" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::ToggleTree,
+            )),
+            "diffoff" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::ClearDiff,
+            )),
+            "scrollbind" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::ToggleLinkedScroll,
+            )),
+            "nextdiff" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::JumpToDiff(DiffJump::Next),
+            )),
+            "prevdiff" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::JumpToDiff(DiffJump::Prev),
+            )),
+            "changes" => Some(AppStateActions::DiffSummaryActions(
+                DiffSummaryModelActions::Toggle,
+            )),
+            "format" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Format(Indent::default()),
+            )),
+            "normalize" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Normalize(Indent::default()),
+            )),
+            "noh" | "nohlsearch" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::ClearSearch,
+            )),
+            "minify" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Minify,
+            )),
+            command if command.starts_with("format ") => match Indent::parse(&command[7..]) {
+                Some(indent) => Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::Format(indent),
+                )),
+                None => show_error(format!(
+                    "Unknown indent '{}', use 2, 4 or tabs",
+                    &command[7..]
+                )),
+            },
+            command if command.starts_with("normalize ") => match Indent::parse(&command[10..]) {
+                Some(indent) => Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::Normalize(indent),
+                )),
+                None => show_error(format!(
+                    "Unknown indent '{}', use 2, 4 or tabs",
+                    &command[10..]
+                )),
+            },
+            "context off" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::SetDiffContext(None),
+            )),
+            command if command.starts_with("context ") => match command[8..].trim().parse() {
+                Ok(context) => Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::SetDiffContext(Some(context)),
+                )),
+                Err(_) => show_error(format!(
+                    "Expected a number of lines or 'off' but found '{}'",
+                    &command[8..]
+                )),
+            },
+            command if command.starts_with("diff ") => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::ProjectDiff(command[5..].trim().to_string()),
+            )),
+            "copypath" => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::CopyPath(None),
+            )),
+            command if command.starts_with("copypath ") => {
+                let mut register = command[9..].trim().chars();
+                match (register.next(), register.next()) {
+                    (Some(name), None) if name.is_ascii_alphabetic() || name == '"' => {
+                        Some(AppStateActions::EditorActions(
+                            EditorContainerModelActions::CopyPath(Some(name)),
+                        ))
+                    }
+                    _ => show_error(format!("Expected a register but found '{}'", &command[9..])),
+                }
+            }
+            command if command.starts_with("query ") => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::Query(command[6..].trim().to_string()),
+            )),
+            command if command.starts_with("set ") => Some(AppStateActions::EditorActions(
+                EditorContainerModelActions::SetDiffOption(command[4..].trim().to_string()),
+            )),
+            "" => None,
+            command => show_error(format!("Unknown command '{}'", command)),
         }
     }
 
@@ -106,6 +241,13 @@ impl Default for CommandBarModel {
         CommandBarModel {
             input: ":".to_string(),
             cursor_position: 1,
+            error: None,
         }
     }
 }
+
+pub fn show_error(error: String) -> Option<AppStateActions> {
+    Some(AppStateActions::CommandBarActions(
+        CommandBarModelActions::ShowError(error),
+    ))
+}
@@ -0,0 +1,52 @@
+use super::app_state::{AppStateActions, BaseActions};
+
+pub enum DiffSummaryModelActions {
+    Toggle,
+    Focus,
+    Unfocus,
+    Select(usize),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DiffSummaryModel {
+    is_open: bool,
+    is_focused: bool,
+    selected_index: usize,
+}
+
+impl DiffSummaryModel {
+    pub fn update(&mut self, action: DiffSummaryModelActions) -> Option<AppStateActions> {
+        match action {
+            DiffSummaryModelActions::Toggle => {
+                self.is_open = !self.is_open;
+                self.is_focused = self.is_open;
+                // the panel takes space away from the editors, they need to know how much is left
+                Some(AppStateActions::BaseAppActions(BaseActions::Resized))
+            }
+            DiffSummaryModelActions::Focus => {
+                self.is_focused = self.is_open;
+                None
+            }
+            DiffSummaryModelActions::Unfocus => {
+                self.is_focused = false;
+                None
+            }
+            DiffSummaryModelActions::Select(index) => {
+                self.selected_index = index;
+                None
+            }
+        }
+    }
+
+    pub fn get_is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn get_is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    pub fn get_selected_index(&self) -> usize {
+        self.selected_index
+    }
+}
@@ -1,10 +1,44 @@
-use std::{cmp::min, usize};
+use std::{cmp::min, collections::HashMap};
 
 use ratatui::layout::Rect;
+use regex::Regex;
+use tracing::{info, warn};
 
-use crate::models::app_state::AppStateActions;
+use crate::{
+    database::db_manager::DatabaseActions,
+    diff::{
+        compute_diff, compute_projected_diff,
+        options::{parse_projection, DiffOptions, DiffProjection},
+        DiffKind, DiffResult, DiffSide,
+    },
+    json::{
+        format::{to_compact_string, to_pretty_string, to_preview_string, Indent},
+        parser::parse,
+        query::{evaluate_query, parse_query},
+        structure::find_containers,
+        value::JsonNode,
+    },
+    models::{
+        app_model::{AppMode, AppModelActions},
+        app_state::AppStateActions,
+        command_bar::show_error,
+        popup::{PasteMode, Popup, PopupModelActions, QueryResult, QUERY_RESULT_PREVIEW_LENGTH},
+    },
+};
 
-use super::editor_model::{EditorCursorDirection, EditorModel, EditorModelActions};
+use super::{
+    editor_model::{normalize_line_breaks, EditorCursorDirection, EditorModel, EditorModelActions},
+    fold_model::{DiffFoldModel, FoldModel},
+    search_model::SearchModel,
+    selection_model::RegisterModel,
+    tree_model::TreeModelActions,
+    types::CursorPosition,
+};
+
+// rows kept around every change when unchanged regions get folded, like `git diff -U3`
+const DEFAULT_DIFF_CONTEXT: usize = 3;
+// whatever was yanked or deleted last ends up here too, it's what p uses by default
+pub const UNNAMED_REGISTER: char = '"';
 
 #[derive(Debug)]
 pub enum EditorFocus {
@@ -12,14 +46,25 @@ pub enum EditorFocus {
     Prev,
 }
 
+#[derive(Debug)]
+pub enum DiffJump {
+    Next,
+    Prev,
+}
+
 #[derive(Debug)]
 pub enum EditorContainerModelActions {
     InitEditor(Rect),
     ToggleResize,
     ResizeEditor(Rect),
     Input(char),
+    Paste(String),
+    // the editor, whether it gets replaced or the text goes in at its cursor, and the text
+    PasteInto(usize, PasteMode, String),
     Undo,
     Redo,
+    EarlierState,
+    LaterState,
     Backspace,
     Enter,
     MoveCursor(EditorCursorDirection),
@@ -27,14 +72,81 @@ pub enum EditorContainerModelActions {
     AddEditor,
     CloseEditor,
     ToggleLines,
+    Diff,
+    // a path like `$.data.users`, only what's under it gets diffed and shown
+    ProjectDiff(String),
+    ClearDiff,
+    ToggleLinkedScroll,
+    JumpToDiff(DiffJump),
+    JumpToLines(Option<usize>, Option<usize>),
+    SetDiffOption(String),
+    LoadDiffOptions(DiffOptions),
+    Format(Indent),
+    Minify,
+    Normalize(Indent),
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    SetDiffContext(Option<usize>),
+    Save,
+    LoadEditors(Vec<EditorModel>),
+    // true for linewise, V instead of v
+    StartSelection(bool),
+    ClearSelection,
+    // visual mode was left some other way, like going to the command bar
+    DropSelections,
+    // the register to use, the unnamed one when there's none
+    Yank(Option<char>),
+    DeleteSelection(Option<char>),
+    // and whether it goes before the cursor
+    Put(Option<char>, bool),
+    // the pattern and whether it goes up, like ?
+    Search(String, bool),
+    // n, or N when reversed
+    FindNext(bool),
+    ClearSearch,
+    // a JSONPath run against every editor, the results show up in a popup
+    Query(String),
+    JumpToEditorLine(usize, usize),
+    // the path under the cursor goes into the register, the unnamed one when there's none
+    CopyPath(Option<char>),
+    // switches the active pane between text and tree
+    ToggleTree,
+    TreeActions(TreeModelActions),
+    // sent once the user stops typing, see refresh()
+    Refresh,
 }
 
 #[derive(Debug, Clone)]
 pub struct EditorContainerModel {
     initialized: bool,
     resized: bool,
+    current_size: Rect,
     active_editor_index: usize,
     editors: Vec<EditorModel>,
+    diff_enabled: bool,
+    diff: Option<DiffResult>,
+    diff_version: usize,
+    diff_options: DiffOptions,
+    diff_projection: Option<DiffProjection>,
+    // typing leaves the diff alone until it stops, like the editors do with parsing
+    is_diff_stale: bool,
+    // while a diff is shown, both panes scroll together over the aligned rows
+    linked_scroll: bool,
+    diff_scroll_offset: usize,
+    // None turns off folding the unchanged regions of a diff
+    diff_context: Option<usize>,
+    diff_folds: Vec<DiffFoldModel>,
+    // first lines, left and right, of the diff folds the user opened, so they
+    // don't close again on the next refresh
+    opened_diff_folds: Vec<(usize, usize)>,
+    // runs of rows a fold hides in either pane as (start, end, hidden rows before
+    // start), rebuilt only when the diff or the folds change
+    hidden_rows: Vec<(usize, usize, usize)>,
+    // the diff version and fold versions of the editors the runs were built for
+    hidden_rows_key: (usize, Vec<usize>),
+    // shared by the editors, that's how text moves from one pane to the other
+    registers: HashMap<char, RegisterModel>,
 }
 
 impl Default for EditorContainerModel {
@@ -42,17 +154,68 @@ impl Default for EditorContainerModel {
         EditorContainerModel {
             initialized: false,
             resized: false,
+            current_size: Rect::default(),
             active_editor_index: 0,
             editors: Vec::from([EditorModel::default()]),
+            diff_enabled: false,
+            diff: None,
+            diff_version: 0,
+            diff_options: DiffOptions::default(),
+            diff_projection: None,
+            is_diff_stale: false,
+            linked_scroll: true,
+            diff_scroll_offset: 0,
+            diff_context: Some(DEFAULT_DIFF_CONTEXT),
+            diff_folds: Vec::new(),
+            opened_diff_folds: Vec::new(),
+            hidden_rows: Vec::new(),
+            hidden_rows_key: (0, Vec::new()),
+            registers: HashMap::new(),
         }
     }
 }
 
 impl EditorContainerModel {
     pub fn update(&mut self, action: EditorContainerModelActions) -> Option<AppStateActions> {
+        let changes_content = matches!(
+            action,
+            EditorContainerModelActions::Input(_)
+                | EditorContainerModelActions::Paste(_)
+                | EditorContainerModelActions::PasteInto(_, _, _)
+                | EditorContainerModelActions::Backspace
+                | EditorContainerModelActions::Enter
+                | EditorContainerModelActions::Undo
+                | EditorContainerModelActions::Redo
+                | EditorContainerModelActions::EarlierState
+                | EditorContainerModelActions::LaterState
+                | EditorContainerModelActions::Format(_)
+                | EditorContainerModelActions::Minify
+                | EditorContainerModelActions::Normalize(_)
+                | EditorContainerModelActions::DeleteSelection(_)
+                | EditorContainerModelActions::Put(_, _)
+        );
+
+        // anything but typing gets to see the documents as they are now
+        if !changes_content {
+            self.refresh();
+        }
+        let result = self.handle_action(action);
+
+        if changes_content && self.diff_enabled {
+            self.is_diff_stale = true;
+        }
+        self.keep_cursor_in_projection();
+        self.refresh_hidden_rows();
+        self.update_diff_scroll();
+
+        result
+    }
+
+    fn handle_action(&mut self, action: EditorContainerModelActions) -> Option<AppStateActions> {
         match action {
             EditorContainerModelActions::InitEditor(rect) => {
                 self.initialized = true;
+                self.current_size = rect;
                 self.editors
                     .iter_mut()
                     .for_each(|editor| editor.resize(rect));
@@ -66,11 +229,52 @@ impl EditorContainerModel {
                 self.editors[self.active_editor_index].handle_action(EditorModelActions::Redo);
                 None
             }
+            EditorContainerModelActions::EarlierState => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::EarlierState);
+                None
+            }
+            EditorContainerModelActions::LaterState => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::LaterState);
+                None
+            }
             EditorContainerModelActions::Input(c) => {
                 self.editors[self.active_editor_index].handle_action(EditorModelActions::Input(c));
                 // self.editors[self.active_editor_index as usize].handle_input(c);
                 None
             }
+            EditorContainerModelActions::Paste(text) => {
+                // with both panes open it's not obvious which one the text is meant for
+                if self.editors.len() > 1 {
+                    return Some(AppStateActions::PopupActions(PopupModelActions::Open(
+                        Popup::PasteTarget {
+                            text,
+                            mode: PasteMode::Insert,
+                        },
+                        self.active_editor_index,
+                    )));
+                }
+
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::InsertText(text));
+                None
+            }
+            EditorContainerModelActions::PasteInto(editor_index, mode, text) => {
+                let Some(editor) = self.editors.get_mut(editor_index) else {
+                    warn!("Cannot paste, there's no editor {}", editor_index);
+                    return None;
+                };
+
+                match mode {
+                    PasteMode::Insert => editor.handle_action(EditorModelActions::InsertText(text)),
+                    PasteMode::Replace => editor.handle_action(EditorModelActions::ReplaceContent(
+                        normalize_line_breaks(&text),
+                    )),
+                }
+                self.active_editor_index = editor_index;
+                None
+            }
             EditorContainerModelActions::Enter => {
                 // self.editors[self.active_editor_index as usize].add_line();
                 self.editors[self.active_editor_index].handle_action(EditorModelActions::AddLine);
@@ -82,19 +286,26 @@ impl EditorContainerModel {
                     .handle_action(EditorModelActions::MoveCursor(direction));
                 None
             }
-            EditorContainerModelActions::ChangeFocus(direction) => match direction {
-                EditorFocus::Next => {
-                    self.active_editor_index = min(
-                        self.editors.len() - 1,
-                        self.active_editor_index.checked_add(1).unwrap_or(0),
-                    );
-                    None
+            EditorContainerModelActions::ChangeFocus(direction) => {
+                // a selection doesn't follow to the other pane, so visual mode ends here
+                let is_selecting = self.editors[self.active_editor_index]
+                    .get_selection()
+                    .is_some();
+                match direction {
+                    EditorFocus::Next => {
+                        self.active_editor_index = min(
+                            self.editors.len() - 1,
+                            self.active_editor_index.checked_add(1).unwrap_or(0),
+                        );
+                    }
+                    EditorFocus::Prev => {
+                        self.active_editor_index = self.active_editor_index.saturating_sub(1);
+                    }
                 }
-                EditorFocus::Prev => {
-                    self.active_editor_index = self.active_editor_index.checked_sub(1).unwrap_or(0);
-                    None
-                }
-            },
+                is_selecting.then_some(AppStateActions::AppModelActions(
+                    AppModelActions::ChangeMode(AppMode::Normal),
+                ))
+            }
             EditorContainerModelActions::ToggleLines => None,
             EditorContainerModelActions::CloseEditor => None,
             EditorContainerModelActions::AddEditor => {
@@ -103,7 +314,9 @@ impl EditorContainerModel {
                 if self.editors.len() >= 2 {
                     return None;
                 }
-                self.editors.push(EditorModel::default());
+                let mut editor = EditorModel::default();
+                editor.resize(self.current_size);
+                self.editors.push(editor);
 
                 None
             }
@@ -117,24 +330,895 @@ impl EditorContainerModel {
             }
             EditorContainerModelActions::ResizeEditor(rect) => {
                 self.resized = false;
+                self.current_size = rect;
                 self.editors
                     .iter_mut()
                     .for_each(|editor| editor.resize(rect));
                 None
             }
+            EditorContainerModelActions::Diff => {
+                if self.editors.len() < 2 {
+                    return show_error("Cannot diff, there's only one editor open".into());
+                }
+
+                self.diff_enabled = true;
+                self.diff_projection = None;
+                self.opened_diff_folds.clear();
+                self.refresh_diff();
+                None
+            }
+            EditorContainerModelActions::ProjectDiff(expression) => self.project_diff(&expression),
+            EditorContainerModelActions::ClearDiff => {
+                self.diff_enabled = false;
+                self.set_diff(None);
+                self.diff_projection = None;
+                self.diff_scroll_offset = 0;
+                self.opened_diff_folds.clear();
+                self.refresh_diff_folds();
+                None
+            }
+            EditorContainerModelActions::ToggleLinkedScroll => {
+                self.linked_scroll = !self.linked_scroll;
+                None
+            }
+            EditorContainerModelActions::JumpToDiff(direction) => {
+                self.jump_to_diff(direction);
+                None
+            }
+            EditorContainerModelActions::JumpToLines(left, right) => {
+                self.jump_to_lines(left, right);
+                None
+            }
+            EditorContainerModelActions::SetDiffOption(setting) => {
+                if let Err(error) = self.diff_options.apply(&setting) {
+                    return show_error(format!("Cannot set diff option: {}", error));
+                }
+
+                if self.diff_enabled {
+                    self.refresh_diff();
+                }
+                Some(AppStateActions::DatabaseActions(
+                    DatabaseActions::SaveDiffOptions(self.diff_options.clone()),
+                ))
+            }
+            EditorContainerModelActions::LoadDiffOptions(options) => {
+                self.diff_options = options;
+                if self.diff_enabled {
+                    self.refresh_diff();
+                }
+                None
+            }
+            EditorContainerModelActions::Format(indent) => {
+                self.rewrite_active_editor(|document| to_pretty_string(document, indent))
+            }
+            EditorContainerModelActions::Minify => self.rewrite_active_editor(to_compact_string),
+            EditorContainerModelActions::Normalize(indent) => self.normalize_editors(indent),
+            EditorContainerModelActions::ToggleFold => {
+                if !self.open_diff_fold_under_cursor() {
+                    self.editors[self.active_editor_index]
+                        .handle_action(EditorModelActions::ToggleFold);
+                    self.mirror_fold();
+                }
+                None
+            }
+            EditorContainerModelActions::FoldAll => {
+                self.for_each_folding_editor(|| EditorModelActions::FoldAll);
+                self.opened_diff_folds.clear();
+                self.refresh_diff_folds();
+                None
+            }
+            EditorContainerModelActions::UnfoldAll => {
+                self.for_each_folding_editor(|| EditorModelActions::UnfoldAll);
+                self.open_diff_folds(|_| true);
+                None
+            }
+            EditorContainerModelActions::Save => Some(AppStateActions::DatabaseActions(
+                DatabaseActions::SaveEditors(self.editors.clone()),
+            )),
+            EditorContainerModelActions::LoadEditors(editors) => {
+                self.load_editors(editors);
+                None
+            }
+            EditorContainerModelActions::StartSelection(is_linewise) => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::StartSelection(is_linewise));
+                let mode = if is_linewise {
+                    AppMode::VisualLine
+                } else {
+                    AppMode::Visual
+                };
+                Some(AppStateActions::AppModelActions(
+                    AppModelActions::ChangeMode(mode),
+                ))
+            }
+            EditorContainerModelActions::ClearSelection => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::ClearSelection);
+                Some(AppStateActions::AppModelActions(
+                    AppModelActions::ChangeMode(AppMode::Normal),
+                ))
+            }
+            EditorContainerModelActions::DropSelections => {
+                self.editors
+                    .iter_mut()
+                    .for_each(|editor| editor.handle_action(EditorModelActions::ClearSelection));
+                None
+            }
+            EditorContainerModelActions::Yank(register) => {
+                let editor = &mut self.editors[self.active_editor_index];
+                if let Some(selected_text) = editor.get_selected_text() {
+                    self.set_register(register, selected_text);
+                }
+                self.handle_action(EditorContainerModelActions::ClearSelection)
+            }
+            EditorContainerModelActions::DeleteSelection(register) => {
+                let editor = &mut self.editors[self.active_editor_index];
+                if let Some(selected_text) = editor.get_selected_text() {
+                    editor.handle_action(EditorModelActions::DeleteSelection);
+                    self.set_register(register, selected_text);
+                }
+                self.handle_action(EditorContainerModelActions::ClearSelection)
+            }
+            EditorContainerModelActions::Put(register, is_before) => {
+                let register = register.unwrap_or(UNNAMED_REGISTER).to_ascii_lowercase();
+                let Some(register) = self.registers.get(&register) else {
+                    return show_error(format!("Cannot put, register {} is empty", register));
+                };
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::Put(register.clone(), is_before));
+                None
+            }
+            EditorContainerModelActions::Search(pattern, is_backward) => {
+                self.search(&pattern, is_backward)
+            }
+            EditorContainerModelActions::FindNext(reverse) => self.find_next(reverse),
+            EditorContainerModelActions::ClearSearch => {
+                self.editors
+                    .iter_mut()
+                    .for_each(|editor| editor.handle_action(EditorModelActions::SetSearch(None)));
+                None
+            }
+            EditorContainerModelActions::Query(expression) => self.query(expression),
+            EditorContainerModelActions::JumpToEditorLine(editor_index, line) => {
+                if editor_index >= self.editors.len() {
+                    warn!("Cannot jump, there's no editor {}", editor_index);
+                    return None;
+                }
+                self.active_editor_index = editor_index;
+                self.jump_active_editor_to((0, line));
+                None
+            }
+            EditorContainerModelActions::ToggleTree => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::ToggleTree);
+                None
+            }
+            EditorContainerModelActions::TreeActions(action) => {
+                self.editors[self.active_editor_index]
+                    .handle_action(EditorModelActions::Tree(action));
+                None
+            }
+            EditorContainerModelActions::CopyPath(register) => {
+                let Some(path) = self.editors[self.active_editor_index].get_cursor_path() else {
+                    return show_error("Cannot copy the path, the editor is not valid JSON".into());
+                };
+                info!("Copied {}", path);
+                self.set_register(register, RegisterModel::new(path, false));
+                None
+            }
+            // update() already caught up before getting here
+            EditorContainerModelActions::Refresh => None,
+            EditorContainerModelActions::SetDiffContext(context) => {
+                self.diff_context = context;
+                self.opened_diff_folds.clear();
+                self.refresh_diff_folds();
+                None
+            }
+        }
+    }
+
+    // both panes show the matches, only the active one moves. An empty pattern
+    // searches for the last one again
+    fn search(&mut self, pattern: &str, is_backward: bool) -> Option<AppStateActions> {
+        let regex = if pattern.is_empty() {
+            let Some(search) = self.editors[self.active_editor_index].get_search() else {
+                return show_error("Cannot search, there's no previous pattern".into());
+            };
+            search.regex.clone()
+        } else {
+            match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(error) => {
+                    // regex points at the problem over a few lines, the status bar has one
+                    let error = error.to_string();
+                    let reason = error.lines().last().unwrap_or_default();
+                    return show_error(format!(
+                        "Invalid search pattern '{}': {}",
+                        pattern,
+                        reason.trim_start_matches("error: ")
+                    ));
+                }
+            }
+        };
+
+        let search = SearchModel::new(regex, is_backward);
+        self.editors.iter_mut().for_each(|editor| {
+            editor.handle_action(EditorModelActions::SetSearch(Some(search.clone())))
+        });
+        self.find_next(false)
+    }
+
+    fn find_next(&mut self, reverse: bool) -> Option<AppStateActions> {
+        let Some((column, line)) = self.editors[self.active_editor_index].find_match(reverse)
+        else {
+            return show_error("Pattern not found".into());
+        };
+
+        self.jump_active_editor_to((column, line));
+        None
+    }
+
+    fn jump_active_editor_to(&mut self, (column, line): CursorPosition) {
+        // folded diff regions have to open up as well, and the other pane follows along
+        if self.diff.is_some() {
+            match self.active_editor_index {
+                0 => self.jump_to_lines(Some(line), None),
+                _ => self.jump_to_lines(None, Some(line)),
+            }
+        }
+        self.editors[self.active_editor_index]
+            .handle_action(EditorModelActions::JumpToPosition((column, line)));
+    }
+
+    // editors that aren't valid JSON are left out, the others get listed together
+    fn query(&self, expression: String) -> Option<AppStateActions> {
+        let segments = match parse_query(&expression) {
+            Ok(segments) => segments,
+            Err(error) => return show_error(format!("Cannot query: {}", error)),
+        };
+
+        let mut results = Vec::new();
+        for (editor_index, editor) in self.editors.iter().enumerate() {
+            let Ok(document) = parse(&editor.get_content()) else {
+                warn!(
+                    "Cannot query editor {}, it's not valid JSON",
+                    editor_index + 1
+                );
+                continue;
+            };
+            results.extend(
+                evaluate_query(&document, &segments)
+                    .into_iter()
+                    .map(|query_match| QueryResult {
+                        editor_index,
+                        path: query_match.path,
+                        value: to_preview_string(query_match.node, QUERY_RESULT_PREVIEW_LENGTH),
+                        line: query_match.node.start_line,
+                    }),
+            );
+        }
+
+        Some(AppStateActions::PopupActions(PopupModelActions::Open(
+            Popup::QueryResults {
+                expression,
+                results,
+            },
+            0,
+        )))
+    }
+
+    // like vim, "a replaces the a register and "A adds to it. The unnamed one always
+    // gets whatever went in last
+    fn set_register(&mut self, register: Option<char>, value: RegisterModel) {
+        match register {
+            Some(name) if name.is_ascii_uppercase() => {
+                let name = name.to_ascii_lowercase();
+                match self.registers.get_mut(&name) {
+                    Some(named_register) => named_register.append(value),
+                    None => {
+                        self.registers.insert(name, value);
+                    }
+                }
+                let appended = self.registers[&name].clone();
+                self.registers.insert(UNNAMED_REGISTER, appended);
+            }
+            Some(name) if name.is_ascii_lowercase() => {
+                self.registers.insert(name, value.clone());
+                self.registers.insert(UNNAMED_REGISTER, value);
+            }
+            _ => {
+                self.registers.insert(UNNAMED_REGISTER, value);
+            }
+        }
+    }
+
+    // a saved workspace replaces whatever is open, which is the empty editor on startup
+    fn load_editors(&mut self, editors: Vec<EditorModel>) {
+        if editors.is_empty() {
+            return;
+        }
+
+        self.editors = editors;
+        self.editors.truncate(2);
+        self.editors
+            .iter_mut()
+            .for_each(|editor| editor.resize(self.current_size));
+        self.active_editor_index = 0;
+        self.opened_diff_folds.clear();
+
+        if self.editors.len() < 2 {
+            self.diff_enabled = false;
+            self.set_diff(None);
+        }
+        if self.diff_enabled {
+            self.refresh_diff();
+        } else {
+            self.refresh_diff_folds();
         }
     }
 
+    // goes through a single ReplaceContent, so the whole rewrite is one undo step
+    fn rewrite_active_editor(
+        &mut self,
+        rewrite: impl Fn(&JsonNode) -> String,
+    ) -> Option<AppStateActions> {
+        let editor = &mut self.editors[self.active_editor_index];
+        match parse(&editor.get_content()) {
+            Ok(document) => {
+                editor.handle_action(EditorModelActions::ReplaceContent(rewrite(&document)));
+                None
+            }
+            Err(error) => show_error(format!(
+                "Cannot rewrite the editor, it's not valid JSON: {}",
+                error
+            )),
+        }
+    }
+
+    // either every editor gets normalized or none does, otherwise the sides
+    // would end up formatted differently and the diff would be even noisier
+    fn normalize_editors(&mut self, indent: Indent) -> Option<AppStateActions> {
+        let mut documents = Vec::with_capacity(self.editors.len());
+        for (index, editor) in self.editors.iter().enumerate() {
+            match parse(&editor.get_content()) {
+                Ok(document) => documents.push(document),
+                Err(error) => {
+                    return show_error(format!(
+                        "Cannot normalize, editor {} is not valid JSON: {}",
+                        index + 1,
+                        error
+                    ));
+                }
+            }
+        }
+
+        for (editor, document) in self.editors.iter_mut().zip(documents) {
+            let content = to_pretty_string(&document.to_sorted(), indent);
+            editor.handle_action(EditorModelActions::ReplaceContent(content));
+        }
+        None
+    }
+
+    fn project_diff(&mut self, expression: &str) -> Option<AppStateActions> {
+        if self.editors.len() < 2 {
+            return show_error("Cannot diff, there's only one editor open".into());
+        }
+        let projection = match parse_projection(expression) {
+            Ok(projection) => projection,
+            Err(error) => return show_error(format!("Cannot diff: {}", error)),
+        };
+        // refresh_diff falls back to the whole documents, here we'd rather tell the user
+        let diff = match compute_projected_diff(
+            &self.editors[0].get_content(),
+            &self.editors[1].get_content(),
+            &projection,
+            &self.diff_options,
+        ) {
+            Ok(diff) => diff,
+            Err(error) => return show_error(format!("Cannot diff {}, {}", expression, error)),
+        };
+        let (left, right) = (
+            diff.find_line_near_row(DiffSide::Left, 0),
+            diff.find_line_near_row(DiffSide::Right, 0),
+        );
+
+        self.diff_enabled = true;
+        self.diff_projection = Some(projection);
+        self.set_diff(Some(diff));
+        self.opened_diff_folds.clear();
+        self.refresh_diff_folds();
+        self.jump_to_lines(left, right);
+        None
+    }
+
+    // parses, refolds and diffs whatever changed since the last time. Every keystroke
+    // doing that would go through the whole documents a few times over
+    fn refresh(&mut self) {
+        self.editors.iter_mut().for_each(|editor| editor.refresh());
+        if self.is_diff_stale {
+            self.refresh_diff();
+        }
+    }
+
+    fn refresh_diff(&mut self) {
+        self.is_diff_stale = false;
+        // we keep diffing while the user types, so the documents will be invalid
+        // pretty often. compute_diff falls back to a line diff in that case
+        let (left, right) = (self.editors[0].get_content(), self.editors[1].get_content());
+        let diff = match &self.diff_projection {
+            Some(projection) => {
+                compute_projected_diff(&left, &right, projection, &self.diff_options)
+                    .unwrap_or_else(|error| {
+                        // the whole documents get diffed until the path works again
+                        info!("Cannot project the diff, {}", error);
+                        compute_diff(&left, &right, &self.diff_options)
+                    })
+            }
+            None => compute_diff(&left, &right, &self.diff_options),
+        };
+        info!("{:?} diff found {} changes", diff.kind, diff.changes.len());
+        self.set_diff(Some(diff));
+        self.refresh_diff_folds();
+    }
+
+    fn set_diff(&mut self, diff: Option<DiffResult>) {
+        self.diff = diff;
+        self.diff_version += 1;
+    }
+
+    fn refresh_diff_folds(&mut self) {
+        let mut diff_folds = match (&self.diff, self.diff_context) {
+            (Some(diff), Some(context)) => self.find_diff_folds(diff, context),
+            _ => Vec::new(),
+        };
+        diff_folds.retain(|fold| {
+            !self
+                .opened_diff_folds
+                .contains(&(fold.left.start_line, fold.right.start_line))
+        });
+        self.diff_folds = diff_folds;
+        self.sync_diff_folds();
+    }
+
+    // everything further than `context` rows from a change gets collapsed. Structural
+    // diffs collapse whole subtrees, so the folds read like the ones from za, text
+    // diffs don't know about those and collapse runs of lines instead
+    fn find_diff_folds(&self, diff: &DiffResult, context: usize) -> Vec<DiffFoldModel> {
+        let row_count = diff.rows.len();
+        let mut is_near_change = vec![false; row_count];
+        for (start_row, end_row) in &diff.hunks {
+            let first_row = start_row.saturating_sub(context);
+            let last_row = min(end_row + context, row_count - 1);
+            is_near_change[first_row..=last_row].fill(true);
+        }
+
+        match diff.kind {
+            DiffKind::Structural => self.find_unchanged_subtrees(diff, &is_near_change),
+            DiffKind::Text => find_unchanged_runs(diff, &is_near_change),
+        }
+    }
+
+    // outermost containers first, so a subtree inside a collapsed one is skipped.
+    // The same subtree has to start and end on the same rows on the other side
+    fn find_unchanged_subtrees(
+        &self,
+        diff: &DiffResult,
+        is_near_change: &[bool],
+    ) -> Vec<DiffFoldModel> {
+        let left_containers = find_containers(&self.editors[0].get_content());
+        let mut right_containers = HashMap::new();
+        for container in find_containers(&self.editors[1].get_content()) {
+            right_containers
+                .entry((
+                    container.start_line,
+                    container.end_line,
+                    container.is_object,
+                ))
+                .or_insert(container);
+        }
+        // hunks are sorted and don't overlap
+        let is_changed_row = |row: usize| {
+            let index = diff.hunks.partition_point(|(_, end_row)| *end_row < row);
+            diff.hunks
+                .get(index)
+                .is_some_and(|(start_row, _)| *start_row <= row)
+        };
+        // rows near a change up to each row, so a subtree is checked without going
+        // through all of its rows
+        let mut near_change_counts = vec![0; is_near_change.len() + 1];
+        for (row, is_near) in is_near_change.iter().enumerate() {
+            near_change_counts[row + 1] = near_change_counts[row] + usize::from(*is_near);
+        }
+
+        let mut diff_folds: Vec<DiffFoldModel> = Vec::new();
+        // containers come sorted by where they start, so the folds starting before
+        // the current one only ever grow
+        let mut folds_before = 0;
+        let mut furthest_folded_row = None;
+        for left_container in left_containers
+            .iter()
+            .filter(|container| container.start_line < container.end_line)
+        {
+            let (Some(start_row), Some(end_row)) = (
+                diff.find_row(DiffSide::Left, left_container.start_line),
+                diff.find_row(DiffSide::Left, left_container.end_line),
+            ) else {
+                continue;
+            };
+            while folds_before < diff_folds.len() && diff_folds[folds_before].start_row < start_row
+            {
+                furthest_folded_row =
+                    furthest_folded_row.max(Some(diff_folds[folds_before].end_row));
+                folds_before += 1;
+            }
+            // the first row stays on screen, so it only has to be unchanged
+            if is_changed_row(start_row)
+                || near_change_counts[end_row + 1] > near_change_counts[start_row + 1]
+                || furthest_folded_row.is_some_and(|row| start_row <= row)
+            {
+                continue;
+            }
+
+            let (Some(right_start_line), Some(right_end_line)) =
+                (diff.rows[start_row].right, diff.rows[end_row].right)
+            else {
+                continue;
+            };
+            let Some(right_container) =
+                right_containers.get(&(right_start_line, right_end_line, left_container.is_object))
+            else {
+                continue;
+            };
+
+            diff_folds.push(DiffFoldModel {
+                start_row,
+                end_row,
+                left: FoldModel::from_container(
+                    left_container,
+                    &self.editors[0]
+                        .get_line(left_container.end_line)
+                        .unwrap_or_default(),
+                ),
+                right: FoldModel::from_container(
+                    right_container,
+                    &self.editors[1]
+                        .get_line(right_container.end_line)
+                        .unwrap_or_default(),
+                ),
+            });
+        }
+
+        diff_folds
+    }
+
+    fn sync_diff_folds(&mut self) {
+        for (editor_index, editor) in self.editors.iter_mut().enumerate() {
+            let folds = self
+                .diff_folds
+                .iter()
+                .map(|diff_fold| diff_fold.get_fold(editor_index).clone())
+                .collect();
+            editor.handle_action(EditorModelActions::SetDiffFolds(folds));
+        }
+    }
+
+    fn open_diff_folds(&mut self, should_open: impl Fn(&DiffFoldModel) -> bool) {
+        let (opened, kept): (Vec<DiffFoldModel>, Vec<DiffFoldModel>) =
+            std::mem::take(&mut self.diff_folds)
+                .into_iter()
+                .partition(|diff_fold| should_open(diff_fold));
+
+        self.opened_diff_folds.extend(
+            opened
+                .iter()
+                .map(|diff_fold| (diff_fold.left.start_line, diff_fold.right.start_line)),
+        );
+        self.diff_folds = kept;
+        self.sync_diff_folds();
+    }
+
+    // while a diff is shown both panes fold together, otherwise only the active one does
+    fn for_each_folding_editor(&mut self, action: impl Fn() -> EditorModelActions) {
+        if self.diff.is_some() {
+            self.editors
+                .iter_mut()
+                .for_each(|editor| editor.handle_action(action()));
+        } else {
+            self.editors[self.active_editor_index].handle_action(action());
+        }
+    }
+
+    // after za, the other pane of a diff folds or opens whatever sits on the same row
+    fn mirror_fold(&mut self) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+        let side = DiffSide::from_editor_index(self.active_editor_index);
+        let other_index = 1 - self.active_editor_index;
+        let cursor_line = self.get_active_cursor_position().1;
+        let Some(other_line) = diff
+            .find_row(side, cursor_line)
+            .and_then(|row| diff.rows[row].get_line(DiffSide::from_editor_index(other_index)))
+        else {
+            return;
+        };
+
+        let is_folded = self.editors[self.active_editor_index]
+            .get_fold(cursor_line)
+            .is_some();
+        self.editors[other_index].handle_action(EditorModelActions::SetFold(other_line, is_folded));
+    }
+
+    // za on a diff fold opens it in both panes, returns false if there's none
+    fn open_diff_fold_under_cursor(&mut self) -> bool {
+        let Some(diff) = &self.diff else {
+            return false;
+        };
+        let side = DiffSide::from_editor_index(self.active_editor_index);
+        let cursor_line = self.get_active_cursor_position().1;
+        let Some(cursor_row) = diff.find_row(side, cursor_line) else {
+            return false;
+        };
+        if !self
+            .diff_folds
+            .iter()
+            .any(|diff_fold| diff_fold.start_row == cursor_row)
+        {
+            return false;
+        }
+
+        self.open_diff_folds(|diff_fold| diff_fold.start_row == cursor_row);
+        true
+    }
+
+    fn jump_to_diff(&mut self, direction: DiffJump) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        let side = DiffSide::from_editor_index(self.active_editor_index);
+        let cursor_line = self.get_active_cursor_position().1;
+        let cursor_row = diff.find_row(side, cursor_line).unwrap_or(0);
+
+        // hunks made only of filler lines on this side put the cursor on the line right
+        // after them, so jumping back to one of those wouldn't move us at all
+        let moves_cursor =
+            |hunk: &&(usize, usize)| diff.find_line_near_row(side, hunk.0) != Some(cursor_line);
+        let target_hunk = match direction {
+            DiffJump::Next => diff
+                .hunks
+                .iter()
+                .filter(|hunk| hunk.0 > cursor_row)
+                .find(moves_cursor),
+            DiffJump::Prev => diff
+                .hunks
+                .iter()
+                .rev()
+                .filter(|hunk| hunk.0 < cursor_row)
+                .find(moves_cursor),
+        };
+        let Some(target_row) = target_hunk.map(|hunk| hunk.0) else {
+            return;
+        };
+
+        self.jump_to_lines(
+            diff.find_line_near_row(DiffSide::Left, target_row),
+            diff.find_line_near_row(DiffSide::Right, target_row),
+        );
+    }
+
+    // moves both panes, when only one of the lines is known the other pane
+    // goes to whatever sits on the same row of the diff
+    fn jump_to_lines(&mut self, left: Option<usize>, right: Option<usize>) {
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        let find_counterpart = |side: DiffSide, line: Option<usize>, other_side: DiffSide| {
+            line.and_then(|line| diff.find_row(side, line))
+                .and_then(|row| diff.find_line_near_row(other_side, row))
+        };
+        let left = left.or_else(|| find_counterpart(DiffSide::Right, right, DiffSide::Left));
+        let right = right.or_else(|| find_counterpart(DiffSide::Left, left, DiffSide::Right));
+
+        // whatever we jump to should be visible in both panes
+        let target_rows: Vec<usize> = [(DiffSide::Left, left), (DiffSide::Right, right)]
+            .into_iter()
+            .filter_map(|(side, line)| line.and_then(|line| diff.find_row(side, line)))
+            .collect();
+        self.open_diff_folds(|diff_fold| {
+            target_rows.iter().any(|row| diff_fold.contains_row(*row))
+        });
+
+        for (editor, target_line) in self.editors.iter_mut().zip([left, right]) {
+            if let Some(line) = target_line {
+                editor.handle_action(EditorModelActions::JumpToLine(line));
+            }
+        }
+    }
+
+    // lines around the projected subtree have no rows, so the cursor can't go there
+    fn keep_cursor_in_projection(&mut self) {
+        let (Some(diff), Some(_)) = (&self.diff, &self.diff_projection) else {
+            return;
+        };
+
+        let side = DiffSide::from_editor_index(self.active_editor_index);
+        let cursor_line = self.get_active_cursor_position().1;
+        if diff.find_row(side, cursor_line).is_some() {
+            return;
+        }
+        let (Some(first_line), Some(last_line)) = (
+            diff.find_line_near_row(side, 0),
+            diff.find_line_near_row(side, diff.rows.len().saturating_sub(1)),
+        ) else {
+            return;
+        };
+
+        self.editors[self.active_editor_index].handle_action(EditorModelActions::JumpToLine(
+            cursor_line.clamp(first_line, last_line),
+        ));
+    }
+
+    // same idea as EditorModel::update_visible_lines, but in the space of aligned rows
+    fn update_diff_scroll(&mut self) {
+        if !self.get_is_scroll_linked() {
+            return;
+        }
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        let side = DiffSide::from_editor_index(self.active_editor_index);
+        let cursor_line = self.get_active_cursor_position().1;
+        let Some(cursor_row) = diff.find_row(side, cursor_line) else {
+            return;
+        };
+
+        let height = self.current_size.height.max(1) as usize;
+        let displayed_row_count = diff.rows.len() - self.count_hidden_rows(diff.rows.len());
+        let cursor_index = cursor_row - self.count_hidden_rows(cursor_row);
+        let mut offset_index =
+            self.diff_scroll_offset - self.count_hidden_rows(self.diff_scroll_offset);
+
+        if cursor_index < offset_index {
+            offset_index = cursor_index;
+        } else if cursor_index >= offset_index + height {
+            offset_index = cursor_index + 1 - height;
+        }
+        offset_index = min(offset_index, displayed_row_count.saturating_sub(height));
+        self.diff_scroll_offset = if offset_index < displayed_row_count {
+            self.find_displayed_row(offset_index)
+        } else {
+            0
+        };
+    }
+
+    fn refresh_hidden_rows(&mut self) {
+        let key = (
+            self.diff_version,
+            self.editors
+                .iter()
+                .map(|editor| editor.get_folds_version())
+                .collect(),
+        );
+        if key == self.hidden_rows_key {
+            return;
+        }
+        self.hidden_rows_key = key;
+        self.hidden_rows.clear();
+        let Some(diff) = &self.diff else {
+            return;
+        };
+
+        // a fold in either pane hides the rows it covers in both, so they stay aligned
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (editor_index, editor) in self.editors.iter().enumerate() {
+            let side = DiffSide::from_editor_index(editor_index);
+            for (start_line, end_line) in editor.get_hidden_lines() {
+                for row in (start_line..=end_line).filter_map(|line| diff.find_row(side, line)) {
+                    match runs.last_mut() {
+                        Some(run) if run.1 + 1 == row => run.1 = row,
+                        _ => runs.push((row, row)),
+                    }
+                }
+            }
+        }
+        runs.sort_unstable();
+
+        let mut hidden_count = 0;
+        for (start, end) in runs {
+            match self.hidden_rows.last_mut() {
+                Some(last) if start <= last.1 + 1 => {
+                    if end > last.1 {
+                        hidden_count += end - last.1;
+                        last.1 = end;
+                    }
+                }
+                _ => {
+                    self.hidden_rows.push((start, end, hidden_count));
+                    hidden_count += end - start + 1;
+                }
+            }
+        }
+    }
+
+    // how many of the rows before `row` are hidden
+    fn count_hidden_rows(&self, row: usize) -> usize {
+        let index = self.hidden_rows.partition_point(|run| run.0 < row);
+        match index.checked_sub(1).map(|index| self.hidden_rows[index]) {
+            Some((start, end, hidden_before)) => hidden_before + min(row, end + 1) - start,
+            None => 0,
+        }
+    }
+
+    // the row shown at `displayed_index`, counting from the first row of the diff
+    fn find_displayed_row(&self, displayed_index: usize) -> usize {
+        // the displayed rows before a run are the ones between it and the start
+        let index = self
+            .hidden_rows
+            .partition_point(|(start, _, hidden_before)| start - hidden_before <= displayed_index);
+        match index.checked_sub(1).map(|index| self.hidden_rows[index]) {
+            Some((start, end, hidden_before)) => displayed_index + hidden_before + end - start + 1,
+            None => displayed_index,
+        }
+    }
+
+    // the last row of the run hiding `row`, if there is one
+    fn find_hidden_run_end(&self, row: usize) -> Option<usize> {
+        let index = self.hidden_rows.partition_point(|run| run.1 < row);
+        self.hidden_rows
+            .get(index)
+            .filter(|(start, _, _)| *start <= row)
+            .map(|(_, end, _)| *end)
+    }
+
+    // which editor line goes on which row of the pane, None marks a filler line
+    // that keeps both sides of a diff aligned
+    pub fn get_display_rows(&self, editor_index: usize) -> Vec<Option<usize>> {
+        match (&self.diff, self.linked_scroll) {
+            (Some(diff), true) => {
+                let side = DiffSide::from_editor_index(editor_index);
+                let height = self.current_size.height as usize;
+                let mut lines = Vec::with_capacity(height);
+                let mut row = self.diff_scroll_offset;
+                while lines.len() < height && row < diff.rows.len() {
+                    match self.find_hidden_run_end(row) {
+                        Some(end) => row = end + 1,
+                        None => {
+                            lines.push(diff.rows[row].get_line(side));
+                            row += 1;
+                        }
+                    }
+                }
+                lines
+            }
+            _ => self.editors[editor_index]
+                .get_display_lines()
+                .into_iter()
+                .map(Some)
+                .collect(),
+        }
+    }
+
+    pub fn get_is_scroll_linked(&self) -> bool {
+        self.diff.is_some() && self.linked_scroll
+    }
+
     pub fn get_editors(&self) -> &Vec<EditorModel> {
         &self.editors
     }
 
+    pub fn get_active_editor(&self) -> &EditorModel {
+        &self.editors[self.active_editor_index]
+    }
+
     pub fn get_active_editor_index(&self) -> usize {
         self.active_editor_index
     }
 
-    pub fn get_active_cursor_position(&self) -> (u16, u16) {
-        self.editors[self.active_editor_index as usize].get_cursor_position()
+    pub fn get_active_cursor_position(&self) -> CursorPosition {
+        self.editors[self.active_editor_index].get_cursor_position()
     }
 
     pub fn get_is_initialized(&self) -> bool {
@@ -144,4 +1228,60 @@ impl EditorContainerModel {
     pub fn get_is_resized_set(&self) -> bool {
         self.resized
     }
+
+    pub fn get_diff_options(&self) -> &DiffOptions {
+        &self.diff_options
+    }
+
+    pub fn get_is_refresh_pending(&self) -> bool {
+        self.is_diff_stale
+            || self
+                .editors
+                .iter()
+                .any(|editor| editor.get_is_refresh_pending())
+    }
+
+    pub fn get_diff(&self) -> Option<&DiffResult> {
+        self.diff.as_ref()
+    }
+
+    pub fn get_diff_projection(&self) -> Option<&DiffProjection> {
+        self.diff_projection.as_ref()
+    }
+}
+
+// rows far from any change always have a line on both sides, fillers are part of hunks
+fn find_unchanged_runs(diff: &DiffResult, is_near_change: &[bool]) -> Vec<DiffFoldModel> {
+    let mut diff_folds = Vec::new();
+    let mut row = 0;
+
+    while row < is_near_change.len() {
+        if is_near_change[row] {
+            row += 1;
+            continue;
+        }
+
+        let start_row = row;
+        while row + 1 < is_near_change.len() && !is_near_change[row + 1] {
+            row += 1;
+        }
+        let end_row = row;
+        row += 1;
+
+        let (start, end) = (diff.rows[start_row], diff.rows[end_row]);
+        if let (Some(left_start), Some(left_end), Some(right_start), Some(right_end)) =
+            (start.left, end.left, start.right, end.right)
+        {
+            if start_row < end_row {
+                diff_folds.push(DiffFoldModel {
+                    start_row,
+                    end_row,
+                    left: FoldModel::from_lines(left_start, left_end),
+                    right: FoldModel::from_lines(right_start, right_end),
+                });
+            }
+        }
+    }
+
+    diff_folds
 }
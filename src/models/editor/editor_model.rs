@@ -1,15 +1,25 @@
-use std::{
-    cmp::{max, min},
-    usize,
-};
+use std::{cmp::min, sync::Arc};
 
-use crossterm::cursor::position;
 use ratatui::layout::Rect;
+use ropey::Rope;
+
+use crate::json::{
+    highlight::HighlightSpan,
+    parser::{parse, ParseError},
+    query::find_path_at_line,
+    structure::{find_containers, ContainerSpan},
+    value::JsonNode,
+};
 
 use super::{
     editor_traits::History,
-    history_model::EditorBackupModel,
-    types::{CursorPosition, EditorLine},
+    fold_model::FoldModel,
+    highlight_model::HighlightModel,
+    history_model::{EditOperation, HistoryModel},
+    search_model::SearchModel,
+    selection_model::{RegisterModel, SelectionModel},
+    tree_model::{TreeModel, TreeModelActions},
+    types::CursorPosition,
 };
 
 #[derive(Debug)]
@@ -28,235 +38,966 @@ pub enum EditorModelActions {
     AddLine,
     Undo,
     Redo,
+    EarlierState,
+    LaterState,
+    JumpToLine(usize),
+    JumpToPosition(CursorPosition),
+    ReplaceContent(String),
+    // pasted text, goes in at the cursor as a single undo step
+    InsertText(String),
+    ToggleFold,
+    FoldAll,
+    UnfoldAll,
+    // folds or opens the container starting on the line
+    SetFold(usize, bool),
+    SetDiffFolds(Vec<FoldModel>),
+    // keeps the anchor of a selection that's already there, so v and V can switch
+    StartSelection(bool),
+    ClearSelection,
+    DeleteSelection,
+    // the register and whether it goes before the cursor, like P
+    Put(RegisterModel, bool),
+    SetSearch(Option<SearchModel>),
+    ToggleTree,
+    Tree(TreeModelActions),
 }
 
 #[derive(Debug, Clone)]
 pub struct EditorModel {
-    data: Vec<EditorLine>,
-    history: Vec<EditorBackupModel>,
-    current_history_index: usize,
+    // line numbers aren't stored anywhere, the rope knows where every line starts.
+    data: Rope,
+    history: HistoryModel,
     current_size: Rect,
-    visible_lines: (u16, u16),
+    visible_lines: (usize, usize),
     cursor_position: CursorPosition,
+    // kept up to date while typing, so the user sees mistakes as they make them
+    parse_error: Option<ParseError>,
+    // the last content that parsed, None while it doesn't. Shared since the whole state
+    // gets cloned for every frame, only validate() swaps it out
+    document: Option<Arc<JsonNode>>,
+    highlights: HighlightModel,
+    // parsing and finding the folds go through the whole document, so typing leaves that
+    // for refresh(), which runs once the user stops for a moment
+    is_refresh_pending: bool,
+    // first lines of the containers the user folded, the folds themselves are
+    // rebuilt from those whenever the content changes
+    folded_lines: Vec<usize>,
+    folds: Vec<FoldModel>,
+    // unchanged regions collapsed by the diff, the container keeps them in sync
+    // with the other pane
+    diff_folds: Vec<FoldModel>,
+    // bumped whenever either kind of fold changes, the container caches which diff
+    // rows are hidden until it does
+    folds_version: usize,
+    selection: Option<SelectionModel>,
+    search: Option<SearchModel>,
+    // the pane shows the document as a tree instead of text while this is set
+    tree: Option<TreeModel>,
 }
 
 impl Default for EditorModel {
     fn default() -> Self {
         let mut editor = EditorModel {
-            data: Vec::from([(1, String::from("awdawd"))]),
-            history: Vec::<EditorBackupModel>::new(),
-            current_history_index: 0,
+            data: Rope::from_str("awdawd"),
+            history: HistoryModel::default(),
             current_size: Rect::default(),
             visible_lines: (0, 1),
             cursor_position: (0, 0),
+            parse_error: None,
+            document: None,
+            highlights: HighlightModel::default(),
+            is_refresh_pending: false,
+            folded_lines: Vec::new(),
+            folds: Vec::new(),
+            diff_folds: Vec::new(),
+            folds_version: 0,
+            selection: None,
+            search: None,
+            tree: None,
         };
 
-        editor.backup();
+        editor.highlights.refresh(&editor.data);
+        editor.validate();
         editor
     }
 }
 
 impl EditorModel {
     pub fn resize(&mut self, rect: Rect) {
-        self.visible_lines = (0, rect.height);
-        self.update_visible_lines(-1);
+        self.current_size = rect;
+        self.update_visible_lines();
+    }
+
+    pub fn get_visible_lines(&self) -> Vec<String> {
+        (self.visible_lines.0..self.visible_lines.1)
+            .filter_map(|line| self.get_line(line))
+            .collect()
+    }
+
+    // the lines on screen from top to bottom, folded lines are skipped
+    pub fn get_display_lines(&self) -> Vec<usize> {
+        let height = self.current_size.height.max(1) as usize;
+        let mut lines = Vec::with_capacity(height);
+        let mut line = Some(self.visible_lines.0).filter(|line| *line < self.get_line_count());
+
+        while let Some(current_line) = line {
+            if lines.len() >= height {
+                break;
+            }
+            lines.push(current_line);
+            line = self.get_next_displayed_line(current_line);
+        }
+        lines
+    }
+
+    // the outermost fold that starts on this line
+    pub fn get_fold(&self, line: usize) -> Option<&FoldModel> {
+        self.get_all_folds()
+            .filter(|fold| fold.start_line == line)
+            .max_by_key(|fold| fold.end_line)
+    }
+
+    pub fn is_line_hidden(&self, line: usize) -> bool {
+        self.get_all_folds().any(|fold| fold.contains(line))
+    }
+
+    // inclusive ranges of the lines under a fold, sorted and merged
+    pub fn get_hidden_lines(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = self
+            .get_all_folds()
+            .map(|fold| (fold.start_line + 1, fold.end_line))
+            .filter(|(start, end)| start <= end)
+            .collect();
+        ranges.sort_unstable();
+
+        let mut hidden_lines: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match hidden_lines.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => hidden_lines.push((start, end)),
+            }
+        }
+        hidden_lines
+    }
+
+    pub fn get_folds_version(&self) -> usize {
+        self.folds_version
+    }
+
+    fn get_all_folds(&self) -> impl Iterator<Item = &FoldModel> {
+        self.folds.iter().chain(self.diff_folds.iter())
+    }
+
+    // hidden lines are represented on screen by the first line of the outermost fold
+    fn get_displayed_line_of(&self, line: usize) -> usize {
+        self.get_all_folds()
+            .filter(|fold| fold.contains(line))
+            .map(|fold| fold.start_line)
+            .min()
+            .unwrap_or(line)
+    }
+
+    fn get_next_displayed_line(&self, line: usize) -> Option<usize> {
+        let next_line = match self.get_fold(line) {
+            Some(fold) => fold.end_line + 1,
+            None => line + 1,
+        };
+        Some(next_line).filter(|line| *line < self.get_line_count())
+    }
+
+    fn get_previous_displayed_line(&self, line: usize) -> Option<usize> {
+        line.checked_sub(1)
+            .map(|previous_line| self.get_displayed_line_of(previous_line))
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.data.len_lines()
+    }
+
+    // without the line break
+    pub fn get_line(&self, line: usize) -> Option<String> {
+        if line >= self.get_line_count() {
+            return None;
+        }
+        let mut text = self.data.line(line).to_string();
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        Some(text)
     }
 
-    pub fn get_visible_lines(&self) -> Vec<EditorLine> {
-        self.data[self.visible_lines.0 as usize..self.visible_lines.1 as usize].to_vec()
+    // in chars, not counting the line break
+    fn get_line_length(&self, line: usize) -> usize {
+        let line_slice = self.data.line(line);
+        let length = line_slice.len_chars();
+        if length > 0 && line_slice.char(length - 1) == '\n' {
+            length - 1
+        } else {
+            length
+        }
     }
 
-    pub fn get_cursor_position(&self) -> (u16, u16) {
+    fn get_cursor_char_index(&self) -> usize {
+        self.data.line_to_char(self.cursor_position.1) + self.cursor_position.0
+    }
+
+    pub fn get_cursor_position(&self) -> CursorPosition {
         self.cursor_position
     }
 
-    fn add_line(&mut self) {
-        let position = self.cursor_position.1 as usize;
-        let last_line_number = self.data[position].0;
-        self.data
-            .insert(position + 1, (last_line_number + 1, String::from("")));
+    pub fn get_content(&self) -> String {
+        self.data.to_string()
+    }
 
-        self.data
-            .iter_mut()
-            .skip(position + 2)
-            .for_each(|line| line.0 += 1);
-        self.cursor_position.1 += 1;
-        self.cursor_position.0 = 0;
+    // start and end of the selection, both included. Linewise ones cover their lines whole
+    pub fn get_selection(&self) -> Option<(CursorPosition, CursorPosition)> {
+        let selection = self.selection.as_ref()?;
+        let (start, end) = selection.get_range(self.cursor_position);
+        if selection.is_linewise {
+            return Some(((0, start.1), (self.get_line_length(end.1), end.1)));
+        }
+        Some((start, end))
+    }
 
-        self.update_visible_lines(1);
+    // the columns of the line that are selected, the end isn't included. A selection going
+    // past the end of a line takes its line break too, that's the extra column
+    pub fn get_selected_columns(&self, line: usize) -> Option<(usize, usize)> {
+        let (start, end) = self.get_selection()?;
+        if line < start.1 || line > end.1 {
+            return None;
+        }
+
+        let start_column = if line == start.1 { start.0 } else { 0 };
+        let end_column = if line == end.1 {
+            end.0 + 1
+        } else {
+            self.get_line_length(line) + 1
+        };
+        Some((start_column, end_column))
     }
 
-    fn delete_line(&mut self) {
-        let position = self.cursor_position.1 as usize;
+    pub fn get_selected_text(&self) -> Option<RegisterModel> {
+        let is_linewise = self.selection.as_ref()?.is_linewise;
+        let (start, end) = self.get_selection()?;
 
-        // we're asked to remove the last line, no point
-        if position == 0 {
-            return;
+        let text = if is_linewise {
+            (start.1..=end.1)
+                .filter_map(|line| self.get_line(line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            let (start_index, end_index) = self.get_selection_char_range()?;
+            self.data.slice(start_index..end_index).to_string()
+        };
+        Some(RegisterModel::new(text, is_linewise))
+    }
+
+    fn get_selection_char_range(&self) -> Option<(usize, usize)> {
+        let is_linewise = self.selection.as_ref()?.is_linewise;
+        let (start, end) = self.get_selection()?;
+        let start_index = self.data.line_to_char(start.1) + start.0;
+        let end_index = min(
+            self.data.line_to_char(end.1) + end.0 + 1,
+            self.data.len_chars(),
+        );
+
+        // the last line has no line break to take with it, the one before it goes instead
+        if is_linewise && end.1 + 1 == self.get_line_count() && start.1 > 0 {
+            return Some((start_index - 1, end_index));
+        }
+        Some((start_index, end_index))
+    }
+
+    pub fn get_search(&self) -> Option<&SearchModel> {
+        self.search.as_ref()
+    }
+
+    pub fn get_search_matches(&self, line: usize) -> Vec<(usize, usize)> {
+        match (&self.search, self.get_line(line)) {
+            (Some(search), Some(text)) => search.find_in_line(&text),
+            _ => Vec::new(),
         }
+    }
+
+    // the next match in the direction of the search, or the other way for N. It wraps
+    // around the document, so the cursor line gets looked at last a second time
+    pub fn find_match(&self, reverse: bool) -> Option<CursorPosition> {
+        let search = self.search.as_ref()?;
+        let is_backward = search.is_backward != reverse;
+        let (column, line) = self.cursor_position;
+        let line_count = self.get_line_count();
 
-        if !self.data[position].1.is_empty() {
-            let previous_position = position.saturating_sub(1);
-            self.data[previous_position].1 = {
-                let mut previous_line = self.data[previous_position].1.clone();
-                previous_line += self.data[position].1.as_str();
-                previous_line
+        let lines: Box<dyn Iterator<Item = usize>> = if is_backward {
+            Box::new((0..=line).rev().chain((line..line_count).rev()))
+        } else {
+            Box::new((line..line_count).chain(0..=line))
+        };
+
+        for (index, current_line) in lines.enumerate() {
+            let matches = self.get_search_matches(current_line);
+            let found = match (index, is_backward) {
+                (0, true) => matches.iter().rev().find(|found| found.0 < column),
+                (0, false) => matches.iter().find(|found| found.0 > column),
+                (_, true) => matches.last(),
+                (_, false) => matches.first(),
+            };
+            if let Some((start_column, _)) = found {
+                return Some((*start_column, current_line));
             }
         }
+        None
+    }
 
-        self.data.remove(position);
-        self.cursor_position.1 -= 1;
-        self.data
-            .iter_mut()
-            .skip(self.cursor_position.1 as usize + 1)
-            .for_each(|line| line.0 -= 1);
-        self.update_visible_lines(-1);
+    pub fn get_parse_error(&self) -> Option<&ParseError> {
+        self.parse_error.as_ref()
     }
 
-    fn update_visible_lines(&mut self, direction: i16) {
-        if self.data.len() >= self.current_size.height.into() {
-            self.visible_lines = (0, self.data.len() as u16);
-        } else {
-            match direction {
-                1 => {
-                    self.visible_lines.0 += 1;
-                    self.visible_lines.1 += 1;
+    // JSONPath of whatever is under the cursor, like `$.data.items[3].price`. The tree
+    // knows exactly which node is selected, the text only knows the line
+    pub fn get_cursor_path(&self) -> Option<String> {
+        let document = self.document.as_ref()?;
+        match &self.tree {
+            Some(tree) => tree.get_selected_row().map(|row| row.path.clone()),
+            None => find_path_at_line(document, self.cursor_position.1),
+        }
+    }
+
+    // syntax colours for the given lines, the view only asks for the ones on screen
+    pub fn get_highlights(&self, lines: &[usize]) -> Vec<Vec<HighlightSpan>> {
+        self.highlights.get_highlights(&self.data, lines)
+    }
+
+    pub fn get_document(&self) -> Option<&JsonNode> {
+        self.document.as_deref()
+    }
+
+    pub fn get_tree(&self) -> Option<&TreeModel> {
+        self.tree.as_ref()
+    }
+
+    // the cursor follows the selected node, so the status bar and a diff
+    // in the other pane keep up with the tree
+    fn update_tree(&mut self, action: TreeModelActions) {
+        let (Some(tree), Some(document)) = (&mut self.tree, &self.document) else {
+            return;
+        };
+        let height = self.current_size.height as usize;
+        tree.update(action, document, height);
+        if let Some(line) = tree.get_selected_row().map(|row| row.start_line) {
+            self.jump_to((0, line));
+        }
+    }
+
+    fn toggle_tree(&mut self) {
+        if self.tree.take().is_some() {
+            return;
+        }
+
+        let mut tree = TreeModel::default();
+        if let Some(document) = &self.document {
+            let height = self.current_size.height as usize;
+            tree.reveal_line(document, self.cursor_position.1, height);
+        }
+        self.tree = Some(tree);
+    }
+
+    fn get_foldable_containers(&self) -> Vec<ContainerSpan> {
+        find_containers(&self.get_content())
+            .into_iter()
+            .filter(|container| container.start_line < container.end_line)
+            .collect()
+    }
+
+    fn refresh_folds(&mut self) {
+        // finding the containers means going through the whole document
+        if self.folded_lines.is_empty() {
+            self.set_folds(Vec::new());
+            return;
+        }
+
+        let containers = self.get_foldable_containers();
+        // lines can hold more than one opening bracket, the first one is the outermost
+        let find_container = |line: usize| {
+            containers
+                .iter()
+                .find(|container| container.start_line == line)
+        };
+
+        self.folded_lines
+            .retain(|line| find_container(*line).is_some());
+        self.folded_lines.sort_unstable();
+        self.folded_lines.dedup();
+
+        let folds = self
+            .folded_lines
+            .iter()
+            .filter_map(|line| find_container(*line))
+            .map(|container| {
+                let end_line_text = self.get_line(container.end_line).unwrap_or_default();
+                FoldModel::from_container(container, &end_line_text)
+            })
+            .collect();
+        self.set_folds(folds);
+    }
+
+    // the version only moves when different lines end up hidden, jumping around
+    // refreshes the folds without changing any of them
+    fn set_folds(&mut self, folds: Vec<FoldModel>) {
+        if !is_same_folding(&self.folds, &folds) {
+            self.folds_version += 1;
+        }
+        self.folds = folds;
+    }
+
+    // za: opens the fold under the cursor, or closes the innermost container around it
+    fn toggle_fold(&mut self) {
+        let cursor_line = self.cursor_position.1;
+        if self.get_fold(cursor_line).is_some() {
+            self.folded_lines.retain(|line| *line != cursor_line);
+            return;
+        }
+
+        let innermost_container = self
+            .get_foldable_containers()
+            .into_iter()
+            .rfind(|container| {
+                container.start_line <= cursor_line && cursor_line <= container.end_line
+            });
+        if let Some(container) = innermost_container {
+            self.folded_lines.push(container.start_line);
+            self.cursor_position = (0, container.start_line);
+        }
+    }
+
+    // zM: everything but the document itself, folding that would leave a single line
+    fn fold_all(&mut self) {
+        self.folded_lines = self
+            .get_foldable_containers()
+            .iter()
+            .filter(|container| container.depth > 0)
+            .map(|container| container.start_line)
+            .collect();
+    }
+
+    // lines were added or removed right after `line`, folds below have to follow. The
+    // folds themselves too, they only get rebuilt once the typing stops
+    fn shift_folded_lines(&mut self, line: usize, delta: isize) {
+        if !self.folds.is_empty() {
+            self.folds_version += 1;
+        }
+        if delta < 0 {
+            self.folded_lines.retain(|folded_line| *folded_line != line);
+            self.folds.retain(|fold| fold.start_line != line);
+        }
+        self.folds.iter_mut().for_each(|fold| {
+            if fold.start_line > line {
+                fold.start_line = fold.start_line.saturating_add_signed(delta);
+            }
+            if fold.end_line > line {
+                fold.end_line = fold.end_line.saturating_add_signed(delta);
+            }
+        });
+        self.folded_lines.iter_mut().for_each(|folded_line| {
+            if *folded_line > line {
+                *folded_line = folded_line.saturating_add_signed(delta);
+            }
+        });
+    }
+
+    fn validate(&mut self) {
+        match parse(&self.get_content()) {
+            Ok(document) => {
+                if let Some(tree) = &mut self.tree {
+                    tree.refresh(&document);
                 }
-                -1 => {
-                    self.visible_lines.0 -= 1;
-                    self.visible_lines.1 -= 1;
+                self.document = Some(Arc::new(document));
+                self.parse_error = None;
+            }
+            Err(error) => {
+                self.document = None;
+                self.parse_error = Some(error);
+            }
+        }
+    }
+
+    // every change to the text goes through here, so it can be undone
+    fn edit(&mut self, operation: EditOperation, cursor_after: CursorPosition) {
+        let cursor_before = self.cursor_position;
+        operation.apply(&mut self.data);
+        self.cursor_position = cursor_after;
+        self.history.record(operation, cursor_before, cursor_after);
+    }
+
+    // lines always get added below the current one, whatever is after the cursor stays put
+    fn add_line(&mut self) {
+        let line = self.cursor_position.1;
+        let line_end = self.data.line_to_char(line) + self.get_line_length(line);
+        self.edit(
+            EditOperation::Insert {
+                char_index: line_end,
+                text: "\n".into(),
+            },
+            (0, line + 1),
+        );
+
+        self.update_visible_lines();
+    }
+
+    // joins the current line to the previous one
+    fn delete_line(&mut self) {
+        let (column, line) = self.cursor_position;
+
+        // we're asked to remove the last line, no point
+        if line == 0 {
+            return;
+        }
+
+        let line_break = self.data.line_to_char(line) - 1;
+        self.edit(
+            EditOperation::Remove {
+                char_index: line_break,
+                text: "\n".into(),
+            },
+            (column, line - 1),
+        );
+        self.update_visible_lines();
+    }
+
+    // the cursor stays on the same line if it still exists, so the view doesn't jump around
+    fn replace_content(&mut self, content: &str) {
+        let new_data = Rope::from_str(content);
+        let cursor_line = min(self.cursor_position.1, new_data.len_lines() - 1);
+        let cursor_column = min(
+            self.cursor_position.0,
+            new_data.line(cursor_line).len_chars(),
+        );
+
+        self.edit(
+            EditOperation::Replace {
+                old: self.data.clone(),
+                new: new_data,
+            },
+            (cursor_column, cursor_line),
+        );
+        self.cursor_position.0 = min(cursor_column, self.get_line_length(cursor_line));
+        self.update_visible_lines();
+    }
+
+    // scrolls just enough to keep the cursor on screen, counting folds as a single line
+    fn update_visible_lines(&mut self) {
+        let height = self.current_size.height.max(1) as usize;
+
+        // the cursor can't stay on a line nobody can see
+        let cursor_line = self.get_displayed_line_of(self.cursor_position.1);
+        if cursor_line != self.cursor_position.1 {
+            self.cursor_position = (0, cursor_line);
+        }
+
+        let walk_back = |line: usize| {
+            let mut first_line = line;
+            for _ in 1..height {
+                match self.get_previous_displayed_line(first_line) {
+                    Some(previous_line) => first_line = previous_line,
+                    None => break,
                 }
-                _ => {}
             }
-            self.visible_lines.0 = max(self.visible_lines.0, 0);
-            self.visible_lines.1 = min(self.visible_lines.1, self.data.len() as u16);
+            first_line
+        };
+
+        let mut first_line =
+            self.get_displayed_line_of(min(self.visible_lines.0, self.get_line_count() - 1));
+        if cursor_line < first_line {
+            first_line = cursor_line;
+        } else {
+            first_line = first_line.max(walk_back(cursor_line));
         }
+
+        // don't leave the bottom of the screen empty after lines got deleted
+        let last_line = self.get_displayed_line_of(self.get_line_count() - 1);
+        first_line = min(first_line, walk_back(last_line));
+
+        self.visible_lines.0 = first_line;
+        let display_lines = self.get_display_lines();
+        self.visible_lines = (
+            first_line,
+            display_lines.last().map_or(first_line, |line| line + 1),
+        );
     }
 
     fn handle_input(&mut self, c: char) {
-        let position = self.cursor_position.1 as usize;
-        self.data[position]
-            .1
-            .insert(self.cursor_position.0 as usize, c);
-        self.move_cursor(EditorCursorDirection::Right);
+        let (column, line) = self.cursor_position;
+        self.edit(
+            EditOperation::Insert {
+                char_index: self.get_cursor_char_index(),
+                text: c.to_string(),
+            },
+            (column + 1, line),
+        );
+    }
+
+    fn insert_text(&mut self, text: &str) {
+        let text = normalize_line_breaks(text);
+        if text.is_empty() {
+            return;
+        }
+
+        let cursor_after = get_end_position(self.cursor_position, &text);
+        self.insert_block(self.get_cursor_char_index(), text, cursor_after);
+    }
+
+    // a block of text that's its own undo step, neither whatever was typed
+    // before nor after should end up with it
+    fn insert_block(&mut self, char_index: usize, text: String, cursor_after: CursorPosition) {
+        self.history.close_group();
+        self.edit(EditOperation::Insert { char_index, text }, cursor_after);
+        self.history.close_group();
+    }
+
+    fn delete_selection(&mut self) {
+        let (Some((start, end)), Some((start_index, end_index))) =
+            (self.get_selection(), self.get_selection_char_range())
+        else {
+            return;
+        };
+        let is_linewise = self
+            .selection
+            .take()
+            .is_some_and(|selection| selection.is_linewise);
+        if start_index == end_index {
+            return;
+        }
+
+        let line_count = self.get_line_count();
+        let removed_text = self.data.slice(start_index..end_index).to_string();
+        let removed_lines = removed_text.matches('\n').count();
+        let cursor_after = if is_linewise {
+            (0, min(start.1, line_count - removed_lines - 1))
+        } else {
+            start
+        };
+
+        self.history.close_group();
+        self.edit(
+            EditOperation::Remove {
+                char_index: start_index,
+                text: removed_text,
+            },
+            cursor_after,
+        );
+        self.history.close_group();
+
+        self.folded_lines
+            .retain(|folded_line| *folded_line < start.1 || *folded_line > end.1);
+        self.folded_lines.iter_mut().for_each(|folded_line| {
+            if *folded_line > end.1 {
+                *folded_line -= removed_lines;
+            }
+        });
+    }
+
+    // linewise registers go on their own lines above or below the cursor, the rest
+    // goes in right before or after the char under it
+    fn put(&mut self, register: RegisterModel, is_before: bool) {
+        let (column, line) = self.cursor_position;
+        let line_breaks = register.text.matches('\n').count();
+
+        if register.is_linewise {
+            let (char_index, text, first_line) = if is_before {
+                (
+                    self.data.line_to_char(line),
+                    format!("{}\n", register.text),
+                    line,
+                )
+            } else {
+                (
+                    self.data.line_to_char(line) + self.get_line_length(line),
+                    format!("\n{}", register.text),
+                    line + 1,
+                )
+            };
+            self.insert_block(char_index, text, (0, first_line));
+            self.folded_lines.iter_mut().for_each(|folded_line| {
+                if *folded_line >= first_line {
+                    *folded_line += line_breaks + 1;
+                }
+            });
+            return;
+        }
+
+        if register.text.is_empty() {
+            return;
+        }
+        let column = if is_before {
+            column
+        } else {
+            min(column + 1, self.get_line_length(line))
+        };
+        // the cursor ends up on the last char that was put, not after it
+        let (end_column, end_line) = get_end_position((column, line), &register.text);
+        self.insert_block(
+            self.data.line_to_char(line) + column,
+            register.text,
+            (end_column.saturating_sub(1), end_line),
+        );
+        self.shift_folded_lines(line, line_breaks as isize);
     }
 
     fn handle_backspace(&mut self) {
-        let (row, col) = self.cursor_position;
-        if row == 0 {
-            return self.delete_line();
+        let (column, line) = self.cursor_position;
+        if column == 0 {
+            self.delete_line();
         } else {
-            let before_char_to_delete = self.data[col as usize].1.chars().take(row as usize - 1);
-            let after_chars_to_delete = self.data[col as usize].1.chars().skip(row as usize);
-            self.data[col as usize] = (
-                self.data[col as usize].0,
-                before_char_to_delete.chain(after_chars_to_delete).collect(),
+            let char_index = self.get_cursor_char_index() - 1;
+            self.edit(
+                EditOperation::Remove {
+                    char_index,
+                    text: self.data.char(char_index).to_string(),
+                },
+                (column - 1, line),
             );
-            self.move_cursor(EditorCursorDirection::Left)
         }
     }
 
+    // the cursor of a history entry was fine for the text back then, folds may hide it now
+    fn restore_cursor(&mut self, cursor: Option<CursorPosition>) {
+        if let Some((column, line)) = cursor {
+            let line = min(line, self.get_line_count() - 1);
+            self.cursor_position = (min(column, self.get_line_length(line)), line);
+        }
+        self.update_visible_lines();
+    }
+
+    fn jump_to(&mut self, (column, line): CursorPosition) {
+        self.history.close_group();
+        let line = min(line, self.get_line_count() - 1);
+        // whatever we jump to should be visible, so the folds around it open up
+        let folds = &self.folds;
+        self.folded_lines.retain(|folded_line| {
+            !folds
+                .iter()
+                .any(|fold| fold.start_line == *folded_line && fold.contains(line))
+        });
+        self.refresh_folds();
+
+        self.cursor_position = (min(column, self.get_line_length(line)), line);
+        self.update_visible_lines();
+    }
+
     fn move_cursor(&mut self, direction: EditorCursorDirection) {
         match direction {
             EditorCursorDirection::Left => {
-                self.cursor_position.0 = self.cursor_position.0.checked_sub(1).unwrap_or(0);
+                self.cursor_position.0 = self.cursor_position.0.saturating_sub(1);
             }
             EditorCursorDirection::Right => {
                 self.cursor_position.0 = min(
                     self.cursor_position.0 + 1,
-                    self.data[self.cursor_position.1 as usize].1.len() as u16,
+                    self.get_line_length(self.cursor_position.1),
                 );
             }
             EditorCursorDirection::Up => {
-                self.cursor_position.1 = self.cursor_position.1.checked_sub(1).unwrap_or(0);
+                if let Some(line) = self.get_previous_displayed_line(self.cursor_position.1) {
+                    self.cursor_position.1 = line;
+                }
                 self.cursor_position.0 = min(
                     self.cursor_position.0,
-                    self.data[self.cursor_position.1 as usize].1.len() as u16,
+                    self.get_line_length(self.cursor_position.1),
                 );
-                self.update_visible_lines(1);
+                self.update_visible_lines();
             }
             EditorCursorDirection::Down => {
-                self.cursor_position.1 =
-                    min(self.cursor_position.1 + 1, self.data.len() as u16 - 1);
+                if let Some(line) = self.get_next_displayed_line(self.cursor_position.1) {
+                    self.cursor_position.1 = line;
+                }
 
                 self.cursor_position.0 = min(
                     self.cursor_position.0,
-                    self.data[self.cursor_position.1 as usize].1.len() as u16,
+                    self.get_line_length(self.cursor_position.1),
                 );
-                self.update_visible_lines(-1);
+                self.update_visible_lines();
             }
         }
     }
 
     pub fn handle_action(&mut self, action: EditorModelActions) {
+        let changes_content = !matches!(
+            action,
+            EditorModelActions::MoveCursor(_)
+                | EditorModelActions::JumpToLine(_)
+                | EditorModelActions::JumpToPosition(_)
+                | EditorModelActions::SetSearch(_)
+                | EditorModelActions::ToggleFold
+                | EditorModelActions::FoldAll
+                | EditorModelActions::UnfoldAll
+                | EditorModelActions::SetFold(_, _)
+                | EditorModelActions::SetDiffFolds(_)
+                | EditorModelActions::StartSelection(_)
+                | EditorModelActions::ClearSelection
+                | EditorModelActions::ToggleTree
+                | EditorModelActions::Tree(_)
+        );
+        let is_line_edit = matches!(
+            action,
+            EditorModelActions::AddLine
+                | EditorModelActions::Backspace
+                | EditorModelActions::InsertText(_)
+        );
+        let line_count = self.get_line_count();
+        let cursor_line = self.cursor_position.1;
+        // ropes share whatever didn't change, so this is cheap
+        let content_before = self.data.clone();
+
         match action {
-            EditorModelActions::Input(char) => {
-                self.handle_input(char);
-                self.backup();
+            EditorModelActions::Input(char) => self.handle_input(char),
+            EditorModelActions::MoveCursor(direction) => {
+                self.move_cursor(direction);
+                self.history.close_group();
             }
-            EditorModelActions::MoveCursor(direction) => self.move_cursor(direction),
-            EditorModelActions::AddLine => {
-                self.add_line();
-                self.backup();
+            EditorModelActions::AddLine => self.add_line(),
+            EditorModelActions::Backspace => self.handle_backspace(),
+            EditorModelActions::Undo => self.undo(),
+            EditorModelActions::Redo => self.redo(),
+            EditorModelActions::EarlierState => self.go_earlier(),
+            EditorModelActions::LaterState => self.go_later(),
+            EditorModelActions::JumpToLine(line) => self.jump_to((0, line)),
+            EditorModelActions::JumpToPosition(position) => self.jump_to(position),
+            EditorModelActions::ToggleFold => {
+                self.toggle_fold();
+                self.refresh_folds();
+                self.update_visible_lines();
             }
-            EditorModelActions::Backspace => {
-                self.handle_backspace();
-                self.backup();
+            EditorModelActions::FoldAll => {
+                self.fold_all();
+                self.refresh_folds();
+                self.update_visible_lines();
             }
-            EditorModelActions::Undo => {
-                self.restore();
+            EditorModelActions::UnfoldAll => {
+                self.folded_lines.clear();
+                self.refresh_folds();
+                self.update_visible_lines();
             }
-            EditorModelActions::Redo => {
-                self.undo_restore();
-                self.update_visible_lines(0);
+            EditorModelActions::SetFold(line, is_folded) => {
+                self.folded_lines.retain(|folded_line| *folded_line != line);
+                if is_folded {
+                    self.folded_lines.push(line);
+                }
+                self.refresh_folds();
+                self.update_visible_lines();
             }
+            EditorModelActions::SetDiffFolds(folds) => {
+                if !is_same_folding(&self.diff_folds, &folds) {
+                    self.folds_version += 1;
+                }
+                self.diff_folds = folds;
+                self.update_visible_lines();
+            }
+            EditorModelActions::ReplaceContent(content) => self.replace_content(&content),
+            EditorModelActions::InsertText(text) => self.insert_text(&text),
+            EditorModelActions::StartSelection(is_linewise) => {
+                let anchor = self
+                    .selection
+                    .as_ref()
+                    .map_or(self.cursor_position, |selection| selection.anchor);
+                self.selection = Some(SelectionModel::new(anchor, is_linewise));
+            }
+            EditorModelActions::ClearSelection => self.selection = None,
+            EditorModelActions::DeleteSelection => self.delete_selection(),
+            EditorModelActions::Put(register, is_before) => self.put(register, is_before),
+            EditorModelActions::SetSearch(search) => self.search = search,
+            EditorModelActions::ToggleTree => self.toggle_tree(),
+            EditorModelActions::Tree(action) => self.update_tree(action),
+        }
+
+        if changes_content {
+            // the anchor could point at text that isn't there anymore
+            self.selection = None;
+            if is_line_edit {
+                let delta = self.get_line_count() as isize - line_count as isize;
+                self.shift_folded_lines(cursor_line, delta);
+            }
+            self.highlights.update(&content_before, &self.data);
+            self.is_refresh_pending = true;
+            self.update_visible_lines();
         }
     }
+
+    // catches up on what typing put off, does nothing if it didn't put anything off
+    pub fn refresh(&mut self) {
+        if !self.is_refresh_pending {
+            return;
+        }
+        self.is_refresh_pending = false;
+        self.validate();
+        self.refresh_folds();
+        self.update_visible_lines();
+    }
+
+    pub fn get_is_refresh_pending(&self) -> bool {
+        self.is_refresh_pending
+    }
 }
 
 impl History for EditorModel {
-    fn restore(&mut self) {
-        // we want to restore the state before the current change, hence -2
-        if let Some(last_entry) = self
-            .history
-            .get(self.current_history_index.saturating_sub(2))
-        {
-            self.data = last_entry.data.clone();
-            self.cursor_position = last_entry.cursor_position;
-            self.current_size = last_entry.current_size;
-            self.visible_lines = last_entry.visible_lines.clone();
-            self.current_history_index = self.current_history_index.saturating_sub(1);
-        }
+    fn undo(&mut self) {
+        let cursor = self.history.undo(&mut self.data);
+        self.restore_cursor(cursor);
     }
 
-    fn undo_restore(&mut self) {
-        if let Some(last_entry) = self.history.get(self.current_history_index) {
-            self.data = last_entry.data.clone();
-            self.cursor_position = last_entry.cursor_position;
-            self.current_size = last_entry.current_size;
-            self.current_history_index = min(self.history.len(), self.current_history_index + 1);
-        }
+    fn redo(&mut self) {
+        let cursor = self.history.redo(&mut self.data);
+        self.restore_cursor(cursor);
     }
 
-    fn backup(&mut self) {
-        // we want to keep the default state of 1 empty line.
-        // allowing the index to go to 0 and then truncating the history
-        // would wipe everything. So then the new state with changes
-        // would become the default state instead
+    fn go_earlier(&mut self) {
+        let cursor = self.history.go_earlier(&mut self.data);
+        self.restore_cursor(cursor);
+    }
 
-        if self.current_history_index >= 1 {
-            self.history.truncate(self.current_history_index);
-        }
+    fn go_later(&mut self) {
+        let cursor = self.history.go_later(&mut self.data);
+        self.restore_cursor(cursor);
+    }
 
-        self.history.push(EditorBackupModel::new(
-            self.data.clone(),
-            self.get_cursor_position(),
-            self.current_size.clone(),
-            self.visible_lines,
-        ));
-        self.current_history_index += 1;
+    fn get_history(&self) -> &HistoryModel {
+        &self.history
+    }
+
+    fn restore_history(&mut self, content: &str, history: HistoryModel) {
+        self.data = Rope::from_str(content);
+        self.cursor_position = history.get_cursor_position();
+        self.history = history;
+        self.folded_lines.clear();
+        self.selection = None;
+
+        self.highlights.refresh(&self.data);
+        self.is_refresh_pending = false;
+        self.validate();
+        self.refresh_folds();
+        self.restore_cursor(Some(self.cursor_position));
     }
 
     fn save(self) -> String {
         "".to_string()
     }
 }
+
+// terminals on windows send \r\n, and some only \r, the rope only breaks lines on \n
+pub fn normalize_line_breaks(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+// where the cursor ends up after typing out the text from `start`
+fn get_end_position(start: CursorPosition, text: &str) -> CursorPosition {
+    let (column, line) = start;
+    match text.rsplit_once('\n') {
+        Some((_, last_line)) => (last_line.chars().count(), line + text.matches('\n').count()),
+        None => (column + text.chars().count(), line),
+    }
+}
+
+fn is_same_folding(left: &[FoldModel], right: &[FoldModel]) -> bool {
+    left.len() == right.len()
+        && left.iter().zip(right).all(|(left_fold, right_fold)| {
+            (left_fold.start_line, left_fold.end_line)
+                == (right_fold.start_line, right_fold.end_line)
+        })
+}
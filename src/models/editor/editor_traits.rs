@@ -1,6 +1,13 @@
+use super::history_model::HistoryModel;
+
 pub trait History {
-    fn restore(&mut self);
-    fn undo_restore(&mut self);
-    fn backup(&mut self);
+    fn undo(&mut self);
+    fn redo(&mut self);
+    // g- and g+, every state in the order it was made, other branches included
+    fn go_earlier(&mut self);
+    fn go_later(&mut self);
+    fn get_history(&self) -> &HistoryModel;
+    // picks up a saved history, the content has to be the text of its current state
+    fn restore_history(&mut self, content: &str, history: HistoryModel);
     fn save(self) -> String;
 }
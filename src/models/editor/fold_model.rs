@@ -0,0 +1,89 @@
+use crate::json::structure::ContainerSpan;
+
+// a collapsed object or array. Its first line stays on screen as a summary,
+// every line after it up to and including end_line is hidden
+#[derive(Debug, Clone)]
+pub struct FoldModel {
+    pub start_line: usize,
+    pub end_line: usize,
+    // chars of the first line shown before the summary, up to the opening bracket
+    pub prefix_length: usize,
+    pub summary: String,
+    // the closing bracket and whatever follows it on the last line
+    pub suffix: String,
+}
+
+impl FoldModel {
+    pub fn new(
+        start_line: usize,
+        end_line: usize,
+        prefix_length: usize,
+        summary: String,
+        suffix: String,
+    ) -> Self {
+        Self {
+            start_line,
+            end_line,
+            prefix_length,
+            summary,
+            suffix,
+        }
+    }
+
+    // `end_line_text` is the line the container closes on
+    pub fn from_container(container: &ContainerSpan, end_line_text: &str) -> Self {
+        let noun = match (container.is_object, container.child_count) {
+            (true, 1) => "key",
+            (true, _) => "keys",
+            (false, 1) => "item",
+            (false, _) => "items",
+        };
+        let suffix: String = end_line_text.chars().skip(container.end_column).collect();
+
+        FoldModel::new(
+            container.start_line,
+            container.end_line,
+            container.start_column + 1,
+            format!(" … {} {} ", container.child_count, noun),
+            suffix.trim_end().to_string(),
+        )
+    }
+
+    // a plain run of lines, nothing of the first one is kept
+    pub fn from_lines(start_line: usize, end_line: usize) -> Self {
+        FoldModel::new(
+            start_line,
+            end_line,
+            0,
+            format!(" ⋯ {} unchanged lines ", end_line - start_line + 1),
+            String::new(),
+        )
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.start_line < line && line <= self.end_line
+    }
+}
+
+// the same fold in both panes of a diff, so they collapse and open together
+#[derive(Debug, Clone)]
+pub struct DiffFoldModel {
+    pub start_row: usize,
+    pub end_row: usize,
+    pub left: FoldModel,
+    pub right: FoldModel,
+}
+
+impl DiffFoldModel {
+    pub fn get_fold(&self, editor_index: usize) -> &FoldModel {
+        if editor_index == 0 {
+            &self.left
+        } else {
+            &self.right
+        }
+    }
+
+    pub fn contains_row(&self, row: usize) -> bool {
+        self.start_row < row && row <= self.end_row
+    }
+}
@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use ropey::Rope;
+
+use crate::json::highlight::{highlight_line, HighlightSpan, HighlightState, MAX_STRING_LINES};
+
+// lines between two remembered states, at most this many get highlighted
+// on top of the visible ones
+const CHECKPOINT_INTERVAL: usize = 64;
+
+// highlighting a line needs the state everything above it left behind. Keeping the spans
+// of the whole document around costs more memory than the document itself, so only the
+// state at every few lines is kept and the lines on screen get highlighted from there
+#[derive(Debug, Clone, Default)]
+pub struct HighlightModel {
+    // (line, state at the start of that line), sorted by line. Shared, the whole
+    // state gets cloned for every frame
+    checkpoints: Arc<Vec<(usize, HighlightState)>>,
+}
+
+impl HighlightModel {
+    pub fn refresh(&mut self, content: &Rope) {
+        let mut checkpoints = Vec::with_capacity(content.len_lines() / CHECKPOINT_INTERVAL + 1);
+        let mut state = HighlightState::default();
+        for line in 0..content.len_lines() {
+            if line % CHECKPOINT_INTERVAL == 0 {
+                checkpoints.push((line, state.clone()));
+            }
+            highlight_line(
+                &get_line(content, line),
+                &mut state,
+                get_lines_after(content, line),
+            );
+        }
+        self.checkpoints = Arc::new(checkpoints);
+    }
+
+    // after an edit only the lines from the edit on get highlighted again, up to the first
+    // checkpoint the state matches again: nothing below it changed
+    pub fn update(&mut self, old_content: &Rope, content: &Rope) {
+        if self.checkpoints.is_empty() {
+            self.refresh(content);
+            return;
+        }
+        let Some((first_line, old_end_line, end_line)) = find_changed_lines(old_content, content)
+        else {
+            return;
+        };
+        let delta = end_line as isize - old_end_line as isize;
+        // the lines above could have been looking for the end of a string in there
+        let restart_line = first_line.saturating_sub(MAX_STRING_LINES);
+
+        let kept = self
+            .checkpoints
+            .partition_point(|(line, _)| *line <= restart_line)
+            .max(1);
+        let mut checkpoints = self.checkpoints[..kept].to_vec();
+        let mut unchanged = self.checkpoints[kept..]
+            .iter()
+            .filter(|(line, _)| *line > old_end_line)
+            .map(|(line, state)| (line.saturating_add_signed(delta), state))
+            .peekable();
+
+        let (mut line, mut state) = checkpoints[kept - 1].clone();
+        let mut last_checkpoint_line = line;
+        while line < content.len_lines() {
+            highlight_line(
+                &get_line(content, line),
+                &mut state,
+                get_lines_after(content, line),
+            );
+            line += 1;
+
+            while unchanged
+                .next_if(|(unchanged_line, _)| *unchanged_line < line)
+                .is_some()
+            {}
+            if let Some((_, unchanged_state)) =
+                unchanged.next_if(|(unchanged_line, _)| *unchanged_line == line)
+            {
+                let is_caught_up = *unchanged_state == state;
+                checkpoints.push((line, state.clone()));
+                last_checkpoint_line = line;
+                if is_caught_up {
+                    checkpoints.extend(unchanged.map(|(line, state)| (line, state.clone())));
+                    break;
+                }
+            } else if line - last_checkpoint_line >= CHECKPOINT_INTERVAL {
+                checkpoints.push((line, state.clone()));
+                last_checkpoint_line = line;
+            }
+        }
+
+        self.checkpoints = Arc::new(checkpoints);
+    }
+
+    // spans for each of `lines`, in the same order. Consecutive lines carry the state
+    // over, anything further away starts again from the closest checkpoint above it
+    pub fn get_highlights(&self, content: &Rope, lines: &[usize]) -> Vec<Vec<HighlightSpan>> {
+        let mut current: Option<(usize, HighlightState)> = None;
+        let mut highlights = Vec::with_capacity(lines.len());
+
+        for &target in lines {
+            if target >= content.len_lines() {
+                highlights.push(Vec::new());
+                continue;
+            }
+
+            let (mut line, mut state) = match current.take() {
+                Some((line, state)) if line <= target && target - line < CHECKPOINT_INTERVAL => {
+                    (line, state)
+                }
+                _ => self.get_checkpoint(target),
+            };
+            while line < target {
+                highlight_line(
+                    &get_line(content, line),
+                    &mut state,
+                    get_lines_after(content, line),
+                );
+                line += 1;
+            }
+
+            highlights.push(highlight_line(
+                &get_line(content, target),
+                &mut state,
+                get_lines_after(content, target),
+            ));
+            current = Some((target + 1, state));
+        }
+
+        highlights
+    }
+
+    fn get_checkpoint(&self, line: usize) -> (usize, HighlightState) {
+        let index = self
+            .checkpoints
+            .partition_point(|(checkpoint_line, _)| *checkpoint_line <= line);
+        match index
+            .checked_sub(1)
+            .and_then(|index| self.checkpoints.get(index))
+        {
+            Some((checkpoint_line, state)) => (*checkpoint_line, state.clone()),
+            None => (0, HighlightState::default()),
+        }
+    }
+}
+
+// the first line an edit touched, and the last one it touched before and after it. Ropes
+// share the chunks an edit didn't touch, so going over those from both ends is quick.
+// None when nothing changed
+fn find_changed_lines(old_content: &Rope, content: &Rope) -> Option<(usize, usize, usize)> {
+    let is_same_chunk =
+        |(old_chunk, chunk): &(&str, &str)| std::ptr::eq(*old_chunk, *chunk) || old_chunk == chunk;
+    let prefix_length: usize = old_content
+        .chunks()
+        .zip(content.chunks())
+        .take_while(is_same_chunk)
+        .map(|(chunk, _)| chunk.len())
+        .sum();
+    if prefix_length == old_content.len_bytes() && prefix_length == content.len_bytes() {
+        return None;
+    }
+
+    let suffix_length: usize = old_content
+        .chunks_at_byte(old_content.len_bytes())
+        .0
+        .reversed()
+        .zip(content.chunks_at_byte(content.len_bytes()).0.reversed())
+        .take_while(is_same_chunk)
+        .map(|(chunk, _)| chunk.len())
+        .sum();
+    // the same chunk can't count for both ends
+    let suffix_length = suffix_length
+        .min(old_content.len_bytes() - prefix_length)
+        .min(content.len_bytes() - prefix_length);
+
+    Some((
+        content.byte_to_line(prefix_length),
+        old_content.byte_to_line(old_content.len_bytes() - suffix_length),
+        content.byte_to_line(content.len_bytes() - suffix_length),
+    ))
+}
+
+fn get_line(content: &Rope, line: usize) -> String {
+    let mut text = content.line(line).to_string();
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    text
+}
+
+fn get_lines_after(content: &Rope, line: usize) -> impl Iterator<Item = String> + '_ {
+    content.lines_at(line + 1).map(|line| line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_end_up_like_a_full_refresh() {
+        let items: Vec<String> = (0..300)
+            .map(|index| format!("  {{\"id\": {}, \"tags\": [\"x\"]}}", index))
+            .collect();
+        let mut content = Rope::from_str(&format!("[\n{}\n]", items.join(",\n")));
+        let mut model = HighlightModel::default();
+        model.refresh(&content);
+
+        // opens a string that the next line closes, adds lines, then takes some away
+        let edits: [(usize, &str, usize); 4] = [
+            (200, "\"note\": \"a\nb\",\n", 0),
+            (5000, "{\n\n", 0),
+            (40, "", 300),
+            (content.len_chars() - 10, "\"", 0),
+        ];
+        for (char_index, text, removed) in edits {
+            let old_content = content.clone();
+            let char_index = char_index.min(content.len_chars());
+            content.remove(char_index..(char_index + removed).min(content.len_chars()));
+            content.insert(char_index, text);
+            model.update(&old_content, &content);
+
+            let mut refreshed = HighlightModel::default();
+            refreshed.refresh(&content);
+            let all_lines: Vec<usize> = (0..content.len_lines()).collect();
+            assert_eq!(
+                model.get_highlights(&content, &all_lines),
+                refreshed.get_highlights(&content, &all_lines)
+            );
+        }
+    }
+
+    #[test]
+    fn lines_far_apart_match_a_full_pass() {
+        let items: Vec<String> = (0..200)
+            .map(|index| format!("  {{\"id\": {},\n  \"note\": \"a\nb\"}}", index))
+            .collect();
+        let content = Rope::from_str(&format!("[\n{}\n]", items.join(",\n")));
+        let mut model = HighlightModel::default();
+        model.refresh(&content);
+
+        let all_lines: Vec<usize> = (0..content.len_lines()).collect();
+        let everything = model.get_highlights(&content, &all_lines);
+        let some_lines = [3, 150, 151, 152, 400, 20, content.len_lines() - 1];
+        let some = model.get_highlights(&content, &some_lines);
+
+        for (line, spans) in some_lines.iter().zip(some) {
+            assert_eq!(spans, everything[*line], "line {}", line);
+        }
+    }
+}
@@ -1,27 +1,628 @@
-use ratatui::layout::Rect;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use super::types::{CursorPosition, EditorLine};
+use ropey::Rope;
 
+use super::types::CursorPosition;
+
+// typing that pauses for longer than this starts a new undo step
+const GROUP_TIMEOUT: Duration = Duration::from_millis(1000);
+// undo steps kept around, the oldest ones go first
+const MAX_HISTORY_SIZE: usize = 1000;
+// the same for the text the steps hold. Rewrites of a big document hold two copies of it
+const MAX_HISTORY_BYTES: usize = 64 * 1024 * 1024;
+
+// a single change to the text, char indexes are into the whole document
 #[derive(Debug, Clone)]
-pub struct EditorBackupModel {
-    pub data: Vec<EditorLine>,
-    pub cursor_position: CursorPosition,
-    pub current_size: Rect,
-    pub visible_lines: (u16, u16), // not sure if I need those
+pub enum EditOperation {
+    Insert { char_index: usize, text: String },
+    Remove { char_index: usize, text: String },
+    // rewrites of the whole document like :format, ropes keep both versions cheap
+    Replace { old: Rope, new: Rope },
 }
 
-impl EditorBackupModel {
+impl EditOperation {
+    pub fn apply(&self, data: &mut Rope) {
+        match self {
+            EditOperation::Insert { char_index, text } => data.insert(*char_index, text),
+            EditOperation::Remove { char_index, text } => {
+                data.remove(*char_index..*char_index + text.chars().count())
+            }
+            EditOperation::Replace { new, .. } => *data = new.clone(),
+        }
+    }
+
+    pub fn revert(&self, data: &mut Rope) {
+        match self {
+            EditOperation::Insert { char_index, text } => {
+                data.remove(*char_index..*char_index + text.chars().count())
+            }
+            EditOperation::Remove { char_index, text } => data.insert(*char_index, text),
+            EditOperation::Replace { old, .. } => *data = old.clone(),
+        }
+    }
+
+    // a saved history might not match the text anymore, ropes panic on indexes past
+    // the end. The text also has to be what the operation says it was
+    fn can_apply(&self, data: &Rope) -> bool {
+        match self {
+            EditOperation::Insert { char_index, .. } => *char_index <= data.len_chars(),
+            EditOperation::Remove { char_index, text } => has_text_at(data, *char_index, text),
+            EditOperation::Replace { old, .. } => data == old,
+        }
+    }
+
+    fn can_revert(&self, data: &Rope) -> bool {
+        match self {
+            EditOperation::Insert { char_index, text } => has_text_at(data, *char_index, text),
+            EditOperation::Remove { char_index, .. } => *char_index <= data.len_chars(),
+            EditOperation::Replace { new, .. } => data == new,
+        }
+    }
+
+    // ropes share most of their text in memory, but the database stores both versions
+    fn get_size(&self) -> usize {
+        match self {
+            EditOperation::Insert { text, .. } | EditOperation::Remove { text, .. } => text.len(),
+            EditOperation::Replace { old, new } => old.len_bytes() + new.len_bytes(),
+        }
+    }
+
+    // where the text ends up once the operation is done, used to tell if the
+    // next one continues right where this one stopped
+    fn get_end_index(&self) -> Option<usize> {
+        match self {
+            EditOperation::Insert { char_index, text } => Some(char_index + text.chars().count()),
+            EditOperation::Remove { char_index, .. } => Some(*char_index),
+            EditOperation::Replace { .. } => None,
+        }
+    }
+}
+
+// an undo step, the operations of a node take the text of its parent to its own
+#[derive(Debug, Clone)]
+pub struct HistoryNode {
+    pub parent: Option<usize>,
+    // the child redo goes to, whichever branch was visited last
+    pub redo_child: Option<usize>,
+    pub operations: Vec<EditOperation>,
+    pub cursor_before: CursorPosition,
+    pub cursor_after: CursorPosition,
+    last_edit: Instant,
+    // rebuilt from the parents when a saved history gets loaded
+    children: Vec<usize>,
+}
+
+impl HistoryNode {
     pub fn new(
-        data: Vec<EditorLine>,
-        cursor_position: CursorPosition,
-        current_size: Rect,
-        visible_lines: (u16, u16),
+        parent: Option<usize>,
+        redo_child: Option<usize>,
+        operations: Vec<EditOperation>,
+        cursor_before: CursorPosition,
+        cursor_after: CursorPosition,
     ) -> Self {
-        Self {
-            data,
-            cursor_position,
-            current_size,
-            visible_lines,
+        HistoryNode {
+            parent,
+            redo_child,
+            operations,
+            cursor_before,
+            cursor_after,
+            last_edit: Instant::now(),
+            children: Vec::new(),
+        }
+    }
+
+    fn get_size(&self) -> usize {
+        self.operations.iter().map(EditOperation::get_size).sum()
+    }
+}
+
+// every state the text has been in, as a tree. Editing after an undo starts a new
+// branch next to the old one instead of throwing it away. Ids grow with every step,
+// so walking them in order goes through the states in the order they were made
+#[derive(Debug, Clone)]
+pub struct HistoryModel {
+    // tells the histories in the database apart, so saving again only writes the
+    // steps that changed. Unique across runs, clones share it
+    key: String,
+    nodes: HashMap<usize, HistoryNode>,
+    root: usize,
+    current: usize,
+    next_id: usize,
+    // bytes of text held by every step, see MAX_HISTORY_BYTES
+    size: usize,
+    // set when the current step can't take any more operations
+    is_group_closed: bool,
+}
+
+impl Default for HistoryModel {
+    fn default() -> Self {
+        let root = HistoryNode::new(None, None, Vec::new(), (0, 0), (0, 0));
+
+        HistoryModel {
+            key: new_history_key(),
+            nodes: HashMap::from([(0, root)]),
+            root: 0,
+            current: 0,
+            next_id: 1,
+            size: 0,
+            is_group_closed: true,
+        }
+    }
+}
+
+impl HistoryModel {
+    // for a history that was saved along with `data`. None if the nodes don't make up
+    // a tree or any of the steps doesn't fit the text it would be applied to
+    pub fn from_nodes(
+        mut nodes: HashMap<usize, HistoryNode>,
+        current: usize,
+        data: &Rope,
+    ) -> Option<Self> {
+        let mut roots = nodes.iter().filter(|(_, node)| node.parent.is_none());
+        let root = *roots.next()?.0;
+        let has_nodes = roots.next().is_none()
+            && nodes.contains_key(&current)
+            && nodes
+                .values()
+                .all(|node| node.parent.is_none_or(|parent| nodes.contains_key(&parent)));
+        if !has_nodes {
+            return None;
+        }
+
+        nodes.values_mut().for_each(|node| node.children.clear());
+        let mut ids: Vec<usize> = nodes.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            if let Some(parent) = nodes[&id].parent {
+                nodes.get_mut(&parent).unwrap().children.push(id);
+            }
+        }
+
+        let mut history = HistoryModel {
+            key: new_history_key(),
+            next_id: nodes.keys().max().map_or(0, |id| id + 1),
+            size: nodes.values().map(HistoryNode::get_size).sum(),
+            nodes,
+            root,
+            current,
+            is_group_closed: true,
+        };
+        if !history.is_tree() || !history.fits(data) {
+            return None;
+        }
+        history.trim();
+        Some(history)
+    }
+
+    // parents pointing at each other make a loop the root never gets to
+    fn is_tree(&self) -> bool {
+        let mut reached = 0;
+        let mut stack = Vec::from([self.root]);
+        while let Some(id) = stack.pop() {
+            reached += 1;
+            let node = &self.nodes[&id];
+            if node
+                .redo_child
+                .is_some_and(|child| !node.children.contains(&child))
+            {
+                return false;
+            }
+            stack.extend(node.children.iter().copied());
+        }
+        reached == self.nodes.len()
+    }
+
+    // goes back to the root from `data`, the current state, and then out to every
+    // other state, the way undo and redo would
+    fn fits(&self, data: &Rope) -> bool {
+        let mut root_data = data.clone();
+        let mut id = self.current;
+        while let Some(parent) = self.nodes[&id].parent {
+            for operation in self.nodes[&id].operations.iter().rev() {
+                if !operation.can_revert(&root_data) {
+                    return false;
+                }
+                operation.revert(&mut root_data);
+            }
+            id = parent;
+        }
+
+        let mut stack = Vec::from([(self.root, root_data)]);
+        while let Some((id, data)) = stack.pop() {
+            for child in &self.nodes[&id].children {
+                let mut child_data = data.clone();
+                for operation in &self.nodes[child].operations {
+                    if !operation.can_apply(&child_data) {
+                        return false;
+                    }
+                    operation.apply(&mut child_data);
+                }
+                stack.push((*child, child_data));
+            }
+        }
+        true
+    }
+
+    pub fn get_key(&self) -> &str {
+        &self.key
+    }
+
+    // a loaded history keeps the key it was saved under
+    pub fn set_key(&mut self, key: String) {
+        self.key = key;
+    }
+
+    pub fn get_nodes(&self) -> &HashMap<usize, HistoryNode> {
+        &self.nodes
+    }
+
+    pub fn get_current(&self) -> usize {
+        self.current
+    }
+
+    // where the cursor was after the change that got us to the current state
+    pub fn get_cursor_position(&self) -> CursorPosition {
+        self.nodes[&self.current].cursor_after
+    }
+
+    // the operation was already applied to the text, cursors are from right before and after it
+    pub fn record(
+        &mut self,
+        operation: EditOperation,
+        cursor_before: CursorPosition,
+        cursor_after: CursorPosition,
+    ) {
+        let now = Instant::now();
+        let current = &self.nodes[&self.current];
+        let continues_group = !self.is_group_closed
+            && self.current != self.root
+            && now.duration_since(current.last_edit) < GROUP_TIMEOUT
+            && current
+                .operations
+                .last()
+                .is_some_and(|last_operation| is_same_group(last_operation, &operation));
+
+        self.size += operation.get_size();
+        if continues_group {
+            let current = self.nodes.get_mut(&self.current).unwrap();
+            current.operations.push(operation);
+            current.cursor_after = cursor_after;
+            current.last_edit = now;
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.nodes.insert(
+            id,
+            HistoryNode {
+                parent: Some(self.current),
+                redo_child: None,
+                operations: Vec::from([operation]),
+                cursor_before,
+                cursor_after,
+                last_edit: now,
+                children: Vec::new(),
+            },
+        );
+        let parent = self.nodes.get_mut(&self.current).unwrap();
+        parent.redo_child = Some(id);
+        parent.children.push(id);
+        self.current = id;
+        self.is_group_closed = false;
+        self.trim();
+    }
+
+    // moving the cursor around ends the current step, like in vim
+    pub fn close_group(&mut self) {
+        self.is_group_closed = true;
+    }
+
+    // returns where the cursor goes, None if there's nothing to undo
+    pub fn undo(&mut self, data: &mut Rope) -> Option<CursorPosition> {
+        let node = &self.nodes[&self.current];
+        let parent = node.parent?;
+        node.operations
+            .iter()
+            .rev()
+            .for_each(|operation| operation.revert(data));
+        let cursor = node.cursor_before;
+
+        self.nodes.get_mut(&parent).unwrap().redo_child = Some(self.current);
+        self.current = parent;
+        self.is_group_closed = true;
+        Some(cursor)
+    }
+
+    pub fn redo(&mut self, data: &mut Rope) -> Option<CursorPosition> {
+        let child = self.nodes[&self.current].redo_child?;
+        self.move_to(child, data)
+    }
+
+    // g-, the state made right before the current one, whatever branch it's on
+    pub fn go_earlier(&mut self, data: &mut Rope) -> Option<CursorPosition> {
+        let target = *self.nodes.keys().filter(|id| **id < self.current).max()?;
+        self.move_to(target, data)
+    }
+
+    // g+
+    pub fn go_later(&mut self, data: &mut Rope) -> Option<CursorPosition> {
+        let target = *self.nodes.keys().filter(|id| **id > self.current).min()?;
+        self.move_to(target, data)
+    }
+
+    // undoes up to the closest node both share, then redoes down to the target
+    fn move_to(&mut self, target: usize, data: &mut Rope) -> Option<CursorPosition> {
+        let mut target_path = Vec::new();
+        let mut node = Some(target);
+        while let Some(id) = node {
+            target_path.push(id);
+            node = self.nodes[&id].parent;
+        }
+
+        let mut cursor = None;
+        while !target_path.contains(&self.current) {
+            cursor = Some(self.undo(data)?);
+        }
+
+        let common_index = target_path.iter().position(|id| *id == self.current)?;
+        for id in target_path[..common_index].iter().rev() {
+            let node = &self.nodes[id];
+            node.operations
+                .iter()
+                .for_each(|operation| operation.apply(data));
+            cursor = Some(node.cursor_after);
+
+            let parent = node.parent.unwrap();
+            self.nodes.get_mut(&parent).unwrap().redo_child = Some(*id);
+            self.current = *id;
+        }
+
+        self.is_group_closed = true;
+        cursor
+    }
+
+    // the oldest step on the way to the current one becomes the new starting point.
+    // Branches that split off before it can't be reached anymore and go with it. The
+    // step that got us to the current state always stays, however big it is
+    fn trim(&mut self) {
+        while self.nodes.len() > MAX_HISTORY_SIZE || self.size > MAX_HISTORY_BYTES {
+            let mut new_root = self.current;
+            let mut path_length = 0;
+            while let Some(parent) = self.nodes[&new_root]
+                .parent
+                .filter(|parent| *parent != self.root)
+            {
+                new_root = parent;
+                path_length += 1;
+            }
+            if new_root == self.root || path_length == 0 {
+                return;
+            }
+
+            let mut removed = Vec::from([self.root]);
+            while let Some(id) = removed.pop() {
+                let node = self.nodes.remove(&id).unwrap();
+                self.size -= node.get_size();
+                removed.extend(node.children.into_iter().filter(|child| *child != new_root));
+            }
+
+            let root = self.nodes.get_mut(&new_root).unwrap();
+            self.size -= root.get_size();
+            root.parent = None;
+            root.operations.clear();
+            self.root = new_root;
+        }
+    }
+}
+
+fn new_history_key() -> String {
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos());
+    format!("{:x}-{:x}", nanos, CREATED.fetch_add(1, Ordering::Relaxed))
+}
+
+fn has_text_at(data: &Rope, char_index: usize, text: &str) -> bool {
+    let end_index = char_index + text.chars().count();
+    end_index <= data.len_chars() && data.slice(char_index..end_index) == text
+}
+
+// typing goes together until a new word starts, deleting goes together until
+// it stops being a single run of backspaces
+fn is_same_group(last_operation: &EditOperation, operation: &EditOperation) -> bool {
+    match (last_operation, operation) {
+        (
+            EditOperation::Insert {
+                text: last_text, ..
+            },
+            EditOperation::Insert { char_index, text },
+        ) => {
+            let starts_word = last_text.ends_with(char::is_whitespace)
+                && text.starts_with(|c: char| !c.is_whitespace());
+            last_operation.get_end_index() == Some(*char_index)
+                && !starts_word
+                && !text.contains('\n')
+                && !last_text.contains('\n')
+        }
+        (EditOperation::Remove { .. }, EditOperation::Remove { char_index, text }) => {
+            last_operation.get_end_index() == Some(*char_index + text.chars().count())
         }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(history: &mut HistoryModel, data: &mut Rope, char_index: usize, text: &str) {
+        let operation = EditOperation::Insert {
+            char_index,
+            text: text.into(),
+        };
+        operation.apply(data);
+        history.record(operation, (0, 0), (0, 0));
+        history.close_group();
+    }
+
+    #[test]
+    fn undo_and_redo_go_back_and_forth() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        insert(&mut history, &mut data, 0, "{}");
+        let operation = EditOperation::Insert {
+            char_index: 1,
+            text: "1".into(),
+        };
+        operation.apply(&mut data);
+        history.record(operation, (1, 0), (2, 0));
+
+        assert_eq!(history.undo(&mut data), Some((1, 0)));
+        assert_eq!(data.to_string(), "{}");
+        assert_eq!(history.undo(&mut data), Some((0, 0)));
+        assert_eq!(data.to_string(), "");
+        assert_eq!(history.undo(&mut data), None);
+
+        assert_eq!(history.redo(&mut data), Some((0, 0)));
+        assert_eq!(history.redo(&mut data), Some((2, 0)));
+        assert_eq!(data.to_string(), "{1}");
+        assert_eq!(history.redo(&mut data), None);
+    }
+
+    #[test]
+    fn typing_a_word_is_a_single_step() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        for (index, c) in "ab cd".chars().enumerate() {
+            let operation = EditOperation::Insert {
+                char_index: index,
+                text: c.to_string(),
+            };
+            operation.apply(&mut data);
+            history.record(operation, (index, 0), (index + 1, 0));
+        }
+
+        history.undo(&mut data);
+        assert_eq!(data.to_string(), "ab ");
+        history.undo(&mut data);
+        assert_eq!(data.to_string(), "");
+    }
+
+    #[test]
+    fn editing_after_an_undo_starts_a_branch() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        insert(&mut history, &mut data, 0, "a");
+        insert(&mut history, &mut data, 1, "b");
+        history.undo(&mut data);
+        insert(&mut history, &mut data, 1, "c");
+        assert_eq!(data.to_string(), "ac");
+
+        // redo follows the newest branch, the old one is still there for g- and g+
+        history.undo(&mut data);
+        history.redo(&mut data);
+        assert_eq!(data.to_string(), "ac");
+        history.go_earlier(&mut data);
+        assert_eq!(data.to_string(), "ab");
+        history.go_earlier(&mut data);
+        assert_eq!(data.to_string(), "a");
+        history.go_later(&mut data);
+        history.go_later(&mut data);
+        assert_eq!(data.to_string(), "ac");
+
+        // going back to the old branch makes it the one redo takes
+        history.go_earlier(&mut data);
+        history.undo(&mut data);
+        history.redo(&mut data);
+        assert_eq!(data.to_string(), "ab");
+    }
+
+    #[test]
+    fn loaded_histories_keep_their_branches() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        insert(&mut history, &mut data, 0, "a");
+        history.undo(&mut data);
+        insert(&mut history, &mut data, 0, "b");
+        let saved_data = data.clone();
+
+        let mut loaded =
+            HistoryModel::from_nodes(history.get_nodes().clone(), history.get_current(), &data)
+                .unwrap();
+        loaded.go_earlier(&mut data);
+        assert_eq!(data.to_string(), "a");
+        loaded.undo(&mut data);
+        assert_eq!(data.to_string(), "");
+
+        let mut nodes = history.get_nodes().clone();
+        nodes.get_mut(&1).unwrap().parent = None;
+        assert!(HistoryModel::from_nodes(nodes, 2, &saved_data).is_none());
+        assert!(HistoryModel::from_nodes(history.get_nodes().clone(), 9, &saved_data).is_none());
+
+        // redo has to go to a child, and undoing from the saved text has to work
+        let mut nodes = history.get_nodes().clone();
+        nodes.get_mut(&1).unwrap().redo_child = Some(2);
+        assert!(HistoryModel::from_nodes(nodes, 2, &saved_data).is_none());
+        let changed_data = Rope::from_str("x");
+        assert!(HistoryModel::from_nodes(history.get_nodes().clone(), 2, &changed_data).is_none());
+    }
+
+    #[test]
+    fn trimming_keeps_the_newest_steps() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        for index in 0..MAX_HISTORY_SIZE + 50 {
+            insert(&mut history, &mut data, index, "a");
+        }
+
+        assert_eq!(history.get_nodes().len(), MAX_HISTORY_SIZE);
+        while history.undo(&mut data).is_some() {}
+        assert_eq!(
+            data.len_chars(),
+            MAX_HISTORY_SIZE + 50 - (MAX_HISTORY_SIZE - 1)
+        );
+    }
+
+    #[test]
+    fn trimming_drops_branches_that_split_off_before_the_new_root() {
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        insert(&mut history, &mut data, 0, "a");
+        history.undo(&mut data);
+        insert(&mut history, &mut data, 0, "b");
+        for index in 1..MAX_HISTORY_SIZE {
+            insert(&mut history, &mut data, index, "c");
+        }
+
+        assert!(history.get_nodes().len() <= MAX_HISTORY_SIZE);
+        assert!(history
+            .get_nodes()
+            .values()
+            .all(|node| node.children.len() <= 1));
+    }
+
+    #[test]
+    fn big_rewrites_count_against_the_size_limit() {
+        let text = Rope::from_str(&"x".repeat(MAX_HISTORY_BYTES / 3));
+        let mut history = HistoryModel::default();
+        let mut data = Rope::new();
+        for _ in 0..3 {
+            let operation = EditOperation::Replace {
+                old: data.clone(),
+                new: text.clone(),
+            };
+            operation.apply(&mut data);
+            history.record(operation, (0, 0), (0, 0));
+        }
+
+        assert!(history.size <= MAX_HISTORY_BYTES);
+        assert!(history.undo(&mut data).is_some());
+        assert!(history.undo(&mut data).is_none());
     }
 }
@@ -1,5 +1,10 @@
 pub mod editor_container_models;
 pub mod editor_model;
 pub mod editor_traits;
+pub mod fold_model;
+pub mod highlight_model;
 pub mod history_model;
+pub mod search_model;
+pub mod selection_model;
+pub mod tree_model;
 pub mod types;
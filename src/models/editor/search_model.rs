@@ -0,0 +1,35 @@
+use regex::Regex;
+
+// the last / or ?, n and N keep using it until the next one
+#[derive(Debug, Clone)]
+pub struct SearchModel {
+    pub regex: Regex,
+    // ? searches up, n follows that and N goes the other way
+    pub is_backward: bool,
+}
+
+impl SearchModel {
+    pub fn new(regex: Regex, is_backward: bool) -> Self {
+        SearchModel { regex, is_backward }
+    }
+
+    // char columns of every match, the end isn't included. Matches don't span lines
+    pub fn find_in_line(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+        // regex works in bytes, the cursor in chars
+        let mut byte_index = 0;
+        let mut column = 0;
+        let mut to_column = |byte: usize| {
+            column += text[byte_index..byte].chars().count();
+            byte_index = byte;
+            column
+        };
+
+        for found in self.regex.find_iter(text) {
+            let start_column = to_column(found.start());
+            let end_column = to_column(found.end());
+            matches.push((start_column, end_column));
+        }
+        matches
+    }
+}
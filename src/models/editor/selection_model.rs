@@ -0,0 +1,52 @@
+use super::types::CursorPosition;
+
+// visual mode, the selection goes from the anchor to wherever the cursor is
+#[derive(Debug, Clone)]
+pub struct SelectionModel {
+    pub anchor: CursorPosition,
+    // whole lines, like V in vim
+    pub is_linewise: bool,
+}
+
+impl SelectionModel {
+    pub fn new(anchor: CursorPosition, is_linewise: bool) -> Self {
+        SelectionModel {
+            anchor,
+            is_linewise,
+        }
+    }
+
+    // the anchor and the cursor in the order they appear in the text, both included
+    pub fn get_range(&self, cursor: CursorPosition) -> (CursorPosition, CursorPosition) {
+        let (anchor_column, anchor_line) = self.anchor;
+        let (cursor_column, cursor_line) = cursor;
+        if (anchor_line, anchor_column) <= (cursor_line, cursor_column) {
+            (self.anchor, cursor)
+        } else {
+            (cursor, self.anchor)
+        }
+    }
+}
+
+// what got yanked or deleted. Linewise text has no trailing line break, putting it
+// adds the line breaks it needs
+#[derive(Debug, Clone)]
+pub struct RegisterModel {
+    pub text: String,
+    pub is_linewise: bool,
+}
+
+impl RegisterModel {
+    pub fn new(text: String, is_linewise: bool) -> Self {
+        RegisterModel { text, is_linewise }
+    }
+
+    // "A and friends add to the register instead of replacing it
+    pub fn append(&mut self, other: RegisterModel) {
+        if self.is_linewise || other.is_linewise {
+            self.text.push('\n');
+            self.is_linewise = true;
+        }
+        self.text.push_str(&other.text);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::json::{
+    format::to_preview_string,
+    query::{find_nodes_at_line, push_path_key},
+    value::{push_pointer_segment, JsonNode, JsonValue},
+};
+
+const ROOT_PATH: &str = "$";
+// values longer than this get cut, the row has to fit on a line anyway
+const TREE_PREVIEW_LENGTH: usize = 120;
+
+#[derive(Debug)]
+pub enum TreeModelActions {
+    // rows to move by, negative ones go up
+    MoveSelection(isize),
+    // opens the selected node, or goes to its first child when it's open already
+    Expand,
+    // closes the selected node, or goes to its parent when there's nothing to close
+    Collapse,
+    Toggle,
+    ExpandAll,
+    CollapseAll,
+}
+
+// a node on screen, the children of an expanded container come right after it
+#[derive(Debug, Clone)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub path: String,
+    // the same path as a JSON pointer, that's how diffs name what changed
+    pub pointer: String,
+    // where each node on the way down sits in its parent, finds the node again
+    // without going through the keys
+    pub indexes: Vec<usize>,
+    // `$` for the root, the key or `[index]` the node sits under otherwise
+    pub label: String,
+    pub is_key: bool,
+    pub is_object: bool,
+    // containers show how many children they have, everything else shows its value
+    pub child_count: Option<usize>,
+    pub type_name: &'static str,
+    pub is_expanded: bool,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+// the tree view of an editor. Nodes are remembered by path, so whatever is expanded
+// stays that way while the document changes under it
+#[derive(Debug, Clone)]
+pub struct TreeModel {
+    expanded_paths: HashSet<String>,
+    selected_index: usize,
+    scroll_offset: usize,
+    // every row, not just the ones on screen. Only rebuilt when the document or
+    // what's expanded changes, and shared since the state gets cloned for every frame
+    rows: Arc<Vec<TreeRow>>,
+}
+
+impl Default for TreeModel {
+    fn default() -> Self {
+        TreeModel {
+            expanded_paths: HashSet::from([ROOT_PATH.to_string()]),
+            selected_index: 0,
+            scroll_offset: 0,
+            rows: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl TreeModel {
+    pub fn update(&mut self, action: TreeModelActions, document: &JsonNode, height: usize) {
+        let rows = Arc::clone(&self.rows);
+        let selected_index = self.get_selected_index();
+        let Some(selected) = rows.get(selected_index) else {
+            return;
+        };
+        // the rows only have to be rebuilt when something opened or closed
+        let mut is_expanded_changed = false;
+        let has_children = selected.child_count.is_some_and(|count| count > 0);
+
+        match action {
+            TreeModelActions::MoveSelection(offset) => {
+                self.selected_index = selected_index
+                    .saturating_add_signed(offset)
+                    .min(rows.len() - 1);
+            }
+            TreeModelActions::Expand => {
+                if selected.is_expanded {
+                    self.selected_index = (selected_index + 1).min(rows.len() - 1);
+                } else if has_children {
+                    is_expanded_changed = self.expanded_paths.insert(selected.path.clone());
+                }
+            }
+            TreeModelActions::Collapse => {
+                if selected.is_expanded {
+                    is_expanded_changed = self.expanded_paths.remove(&selected.path);
+                } else if let Some(parent_index) = rows[..selected_index]
+                    .iter()
+                    .rposition(|row| row.depth + 1 == selected.depth)
+                {
+                    self.selected_index = parent_index;
+                }
+            }
+            TreeModelActions::Toggle => {
+                if selected.is_expanded {
+                    is_expanded_changed = self.expanded_paths.remove(&selected.path);
+                } else if has_children {
+                    is_expanded_changed = self.expanded_paths.insert(selected.path.clone());
+                }
+            }
+            TreeModelActions::ExpandAll => {
+                push_container_paths(document, ROOT_PATH.to_string(), &mut self.expanded_paths);
+                is_expanded_changed = true;
+            }
+            TreeModelActions::CollapseAll => {
+                self.expanded_paths = HashSet::from([ROOT_PATH.to_string()]);
+                self.selected_index = 0;
+                is_expanded_changed = true;
+            }
+        }
+
+        if is_expanded_changed {
+            self.refresh(document);
+        }
+        self.scroll_to_selected(height);
+    }
+
+    // opens everything above the node on `line` and selects it, so switching to the
+    // tree keeps the spot the cursor was at
+    pub fn reveal_line(&mut self, document: &JsonNode, line: usize, height: usize) {
+        let mut nodes = find_nodes_at_line(document, line);
+        let Some(target) = nodes.pop() else {
+            return;
+        };
+        self.expanded_paths
+            .extend(nodes.into_iter().map(|query_match| query_match.path));
+        self.refresh(document);
+
+        self.selected_index = self
+            .rows
+            .iter()
+            .position(|row| row.path == target.path)
+            .unwrap_or(0);
+        self.scroll_to_selected(height);
+    }
+
+    fn scroll_to_selected(&mut self, height: usize) {
+        let height = height.max(1);
+        if self.selected_index < self.scroll_offset {
+            self.scroll_offset = self.selected_index;
+        } else if self.selected_index >= self.scroll_offset + height {
+            self.scroll_offset = self.selected_index + 1 - height;
+        }
+    }
+
+    // the document changed, or what's expanded did
+    pub fn refresh(&mut self, document: &JsonNode) {
+        let mut rows = Vec::new();
+        self.push_rows(
+            document,
+            (ROOT_PATH.to_string(), String::new(), Vec::new()),
+            (ROOT_PATH.to_string(), false),
+            &mut rows,
+        );
+        self.rows = Arc::new(rows);
+    }
+
+    fn push_rows(
+        &self,
+        node: &JsonNode,
+        (path, pointer, indexes): (String, String, Vec<usize>),
+        (label, is_key): (String, bool),
+        rows: &mut Vec<TreeRow>,
+    ) {
+        let child_count = match &node.value {
+            JsonValue::Array(items) => Some(items.len()),
+            JsonValue::Object(members) => Some(members.len()),
+            _ => None,
+        };
+        let is_expanded =
+            child_count.is_some_and(|count| count > 0) && self.expanded_paths.contains(&path);
+
+        let depth = indexes.len();
+        rows.push(TreeRow {
+            depth,
+            path: path.clone(),
+            pointer: pointer.clone(),
+            indexes: indexes.clone(),
+            label,
+            is_key,
+            is_object: matches!(node.value, JsonValue::Object(_)),
+            child_count,
+            type_name: node.value.get_type_name(),
+            is_expanded,
+            start_line: node.start_line,
+            end_line: node.end_line,
+        });
+
+        if !is_expanded {
+            return;
+        }
+        match &node.value {
+            JsonValue::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.push_rows(
+                        item,
+                        (
+                            format!("{}[{}]", path, index),
+                            push_pointer_segment(&pointer, &index.to_string()),
+                            [indexes.as_slice(), &[index]].concat(),
+                        ),
+                        (format!("[{}]", index), false),
+                        rows,
+                    );
+                }
+            }
+            JsonValue::Object(members) => {
+                for (index, (key, member)) in members.iter().enumerate() {
+                    self.push_rows(
+                        member,
+                        (
+                            push_path_key(&path, key),
+                            push_pointer_segment(&pointer, key),
+                            [indexes.as_slice(), &[index]].concat(),
+                        ),
+                        (key.clone(), true),
+                        rows,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // edits can leave fewer rows than there were, the last one is selected then
+    pub fn get_selected_index(&self) -> usize {
+        self.selected_index.min(self.rows.len().saturating_sub(1))
+    }
+
+    pub fn get_selected_row(&self) -> Option<&TreeRow> {
+        self.rows.get(self.get_selected_index())
+    }
+
+    pub fn get_rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    pub fn get_scroll_offset(&self) -> usize {
+        self.scroll_offset
+    }
+}
+
+// the value a row shows, only worked out for the rows on screen
+pub fn get_preview(document: &JsonNode, row: &TreeRow) -> String {
+    let node = row
+        .indexes
+        .iter()
+        .try_fold(document, |node, index| match &node.value {
+            JsonValue::Array(items) => items.get(*index),
+            JsonValue::Object(members) => members.get(*index).map(|(_, member)| member),
+            _ => None,
+        });
+    node.map_or(String::new(), |node| {
+        to_preview_string(node, TREE_PREVIEW_LENGTH)
+    })
+}
+
+fn push_container_paths(node: &JsonNode, path: String, paths: &mut HashSet<String>) {
+    match &node.value {
+        JsonValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                push_container_paths(item, format!("{}[{}]", path, index), paths);
+            }
+        }
+        JsonValue::Object(members) => {
+            for (key, member) in members {
+                push_container_paths(member, push_path_key(&path, key), paths);
+            }
+        }
+        _ => return,
+    }
+    paths.insert(path);
+}
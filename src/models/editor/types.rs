@@ -1,3 +1 @@
-pub type LineNumber = usize;
-pub type EditorLine = (LineNumber, String);
-pub type CursorPosition = (u16, u16);
+pub type CursorPosition = (usize, usize);
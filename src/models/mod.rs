@@ -2,6 +2,8 @@ pub mod app_model;
 pub mod app_state;
 pub mod base_model;
 pub mod command_bar;
+pub mod diff_summary;
 pub mod editor;
 pub mod model_manager;
+pub mod popup;
 pub mod router;
@@ -1,8 +1,19 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
-use std::sync::mpsc;
 use std::thread;
+use std::time::Duration;
 
-use super::app_state::{AppState, AppStateActions};
+use tracing::error;
+
+use crate::database::db_manager::{DBManager, DatabaseActions};
+
+use super::{
+    app_state::{AppState, AppStateActions},
+    editor::editor_container_models::EditorContainerModelActions,
+};
+
+// how long the user has to stop typing before the documents get parsed and diffed again
+const REFRESH_DELAY: Duration = Duration::from_millis(150);
 
 #[derive(Debug)]
 pub struct ModelManager {
@@ -16,10 +27,53 @@ impl ModelManager {
         let app_state = Arc::new(Mutex::new(app_state));
 
         let app_state_clone = Arc::clone(&app_state);
-        thread::spawn(move || loop {
-            if let Ok(command) = receiver.recv() {
+        thread::spawn(move || {
+            // the connection can't be shared between threads, so it lives and dies here
+            let database = match DBManager::new() {
+                Ok(database) => {
+                    database.migrate_schema();
+                    Some(database)
+                }
+                Err(_) => {
+                    error!("Cannot open the database, nothing will be persisted");
+                    None
+                }
+            };
+
+            let mut startup_commands = Vec::from([
+                AppStateActions::DatabaseActions(DatabaseActions::LoadDiffOptions),
+                AppStateActions::DatabaseActions(DatabaseActions::LoadEditors),
+            ])
+            .into_iter();
+            let mut is_refresh_pending = false;
+            loop {
+                let command = match startup_commands.next() {
+                    Some(command) => command,
+                    None if is_refresh_pending => match receiver.recv_timeout(REFRESH_DELAY) {
+                        Ok(command) => command,
+                        Err(RecvTimeoutError::Timeout) => {
+                            AppStateActions::EditorActions(EditorContainerModelActions::Refresh)
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    },
+                    // stops once the ui is gone and the sender got dropped
+                    None => match receiver.recv() {
+                        Ok(command) => command,
+                        Err(_) => break,
+                    },
+                };
                 let mut app_state = app_state_clone.lock().unwrap();
                 app_state.update(command);
+
+                for database_action in app_state.take_database_actions() {
+                    let Some(database) = &database else {
+                        continue;
+                    };
+                    if let Some(result) = database.handle_action(database_action) {
+                        app_state.update(result);
+                    }
+                }
+                is_refresh_pending = app_state.editor_store.get_is_refresh_pending();
             }
         });
 
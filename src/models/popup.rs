@@ -0,0 +1,106 @@
+use super::{
+    app_state::AppStateActions, editor::editor_container_models::EditorContainerModelActions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    Insert,
+    Replace,
+}
+
+// previews longer than this get cut, a popup line doesn't fit more
+pub const QUERY_RESULT_PREVIEW_LENGTH: usize = 30;
+
+// a node :query found, in one of the editors
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    pub editor_index: usize,
+    pub path: String,
+    // the start of the value, whole subtrees can match
+    pub value: String,
+    pub line: usize,
+}
+
+// what the popup is asking about, it holds on to whatever it needs once the user picks
+#[derive(Debug, Clone)]
+pub enum Popup {
+    PasteTarget {
+        text: String,
+        mode: PasteMode,
+    },
+    QueryResults {
+        expression: String,
+        results: Vec<QueryResult>,
+    },
+}
+
+pub enum PopupModelActions {
+    // the popup and the option selected when it opens
+    Open(Popup, usize),
+    Close,
+    Select(usize),
+    TogglePasteMode,
+    Confirm,
+}
+
+// a modal on top of whatever view is showing, while it's open it gets every key
+#[derive(Debug, Default, Clone)]
+pub struct PopupModel {
+    popup: Option<Popup>,
+    selected_index: usize,
+}
+
+impl PopupModel {
+    pub fn update(&mut self, action: PopupModelActions) -> Option<AppStateActions> {
+        match action {
+            PopupModelActions::Open(popup, selected_index) => {
+                self.popup = Some(popup);
+                self.selected_index = selected_index;
+                None
+            }
+            PopupModelActions::Close => {
+                self.popup = None;
+                None
+            }
+            PopupModelActions::Select(index) => {
+                self.selected_index = index;
+                None
+            }
+            PopupModelActions::TogglePasteMode => {
+                if let Some(Popup::PasteTarget { mode, .. }) = &mut self.popup {
+                    *mode = match mode {
+                        PasteMode::Insert => PasteMode::Replace,
+                        PasteMode::Replace => PasteMode::Insert,
+                    };
+                }
+                None
+            }
+            PopupModelActions::Confirm => match self.popup.take()? {
+                Popup::PasteTarget { text, mode } => Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::PasteInto(self.selected_index, mode, text),
+                )),
+                Popup::QueryResults { results, .. } => {
+                    let result = results.get(self.selected_index)?;
+                    Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::JumpToEditorLine(
+                            result.editor_index,
+                            result.line,
+                        ),
+                    ))
+                }
+            },
+        }
+    }
+
+    pub fn get_popup(&self) -> Option<&Popup> {
+        self.popup.as_ref()
+    }
+
+    pub fn get_is_open(&self) -> bool {
+        self.popup.is_some()
+    }
+
+    pub fn get_selected_index(&self) -> usize {
+        self.selected_index
+    }
+}
@@ -62,6 +62,8 @@ impl View for CommandBar {
             let (mode_text, mode_color) = match app_state_mode {
                 AppMode::Editing => ("Editing", Style::default().bg(Color::Green)),
                 AppMode::Normal => ("Normal", Style::default().bg(Color::Blue)),
+                AppMode::Visual => ("Visual", Style::default().bg(Color::Magenta)),
+                AppMode::VisualLine => ("V-Line", Style::default().bg(Color::Magenta)),
                 _ => ("", Style::default()),
             };
 
@@ -71,10 +73,35 @@ impl View for CommandBar {
                 .style(mode_color);
             frame.render_widget(mode_paragraph, status_bar_layout[0]);
 
-            let middle_paragraph = Paragraph::default().style(Style::default().bg(Color::Black));
+            let is_editor_route = app_state.router_store.get_current_route() == "/editor";
+            let active_editor = app_state.editor_store.get_active_editor();
+            // whatever the last command got wrong comes before what's wrong with the text
+            let error = app_state
+                .command_bar_store
+                .get_error()
+                .map(String::from)
+                .or_else(|| {
+                    active_editor
+                        .get_parse_error()
+                        .filter(|_| is_editor_route)
+                        .map(|error| error.to_string())
+                });
+            let cursor_path = active_editor.get_cursor_path().filter(|_| is_editor_route);
+            let middle_paragraph = match (error, cursor_path) {
+                (Some(error), _) => Paragraph::new(Text::from(Line::from(format!(" ✗ {}", error))))
+                    .style(Style::default().bg(Color::Black).fg(Color::Red)),
+                (None, Some(path)) => Paragraph::new(Text::from(Line::from(format!(
+                    " {}",
+                    truncate_start(&path, status_bar_layout[1].width.saturating_sub(1) as usize)
+                ))))
+                .style(Style::default().bg(Color::Black).fg(Color::Gray)),
+                (None, None) => Paragraph::default().style(Style::default().bg(Color::Black)),
+            };
             frame.render_widget(middle_paragraph, status_bar_layout[1]);
 
-            let lines_widget = Text::from(Line::from(" 10:80 "));
+            // 1 based, like every other editor shows them
+            let (column, line) = app_state.editor_store.get_active_cursor_position();
+            let lines_widget = Text::from(Line::from(format!(" {}:{} ", line + 1, column + 1)));
             let lines_widget = Paragraph::new(lines_widget)
                 .alignment(Alignment::Center)
                 .style(Style::default().bg(Color::Blue));
@@ -128,3 +155,22 @@ impl View for CommandBar {
         false
     }
 }
+
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_length).collect();
+    truncated.push('…');
+    truncated
+}
+
+// deep paths lose their start rather than the key the cursor is on
+fn truncate_start(text: &str, max_length: usize) -> String {
+    let length = text.chars().count();
+    if length <= max_length {
+        return text.to_string();
+    }
+    let kept: String = text.chars().skip(length + 1 - max_length.max(1)).collect();
+    format!("…{}", kept)
+}
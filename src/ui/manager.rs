@@ -6,7 +6,7 @@ use std::{
 };
 
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste, KeyCode, KeyModifiers};
 use crossterm::{
     event::KeyCode::Char,
     execute,
@@ -16,6 +16,7 @@ use ratatui::prelude::*;
 
 use crate::models::app_model::{AppMode, AppModelActions};
 use crate::models::app_state::{AppStateActions, BaseActions};
+use crate::models::editor::editor_container_models::EditorContainerModelActions;
 use crate::models::model_manager::ModelManager;
 use crate::{
     event::{Event, EventHandler},
@@ -23,6 +24,7 @@ use crate::{
 };
 
 use super::views::editor_view::EditorView;
+use super::views::popup_view::PopupView;
 use super::views::view::{TabState, ViewContext};
 use super::{
     command_bar::view::CommandBar,
@@ -56,6 +58,7 @@ impl UiManager {
         routes_map.insert("/editor".into(), Box::new(editor_view));
 
         let mut command_bar = CommandBar::new();
+        let mut popup_view = PopupView::new();
 
         app_state
             .router_store
@@ -80,6 +83,7 @@ impl UiManager {
                             frame,
                             view,
                             &command_bar,
+                            &popup_view,
                             &current_app_state,
                             &state_manager,
                         )
@@ -99,13 +103,23 @@ impl UiManager {
                         _ => TabState::None,
                     };
 
+                    let context = ViewContext::new(is_ctrl_pressed, is_shift_pressed, tab_state);
+                    // a popup is modal, nothing under it gets the keys until it's gone
+                    if current_app_state.popup_store.get_is_open() {
+                        if let Some(command) =
+                            popup_view.handle_event(&key_event, context, &current_app_state)
+                        {
+                            state_manager.update(command);
+                        }
+                        continue;
+                    }
+
                     // if we get a signal : and we're in normal, we should change into command mode
                     if let (Char(':'), AppMode::Normal) = (input_keycode, app_mode) {
                         state_manager.update(AppStateActions::AppModelActions(
                             AppModelActions::ChangeMode(AppMode::Command),
                         ));
                     }
-                    let context = ViewContext::new(is_ctrl_pressed, is_shift_pressed, tab_state);
                     match app_mode {
                         AppMode::Command => {
                             if let Some(command) =
@@ -133,7 +147,18 @@ impl UiManager {
                 Event::Resize(_, _) => {
                     state_manager.update(AppStateActions::BaseAppActions(BaseActions::Resized))
                 }
-                Event::Paste(_) => {}
+                Event::Paste(text) => {
+                    let current_app_state = { state_manager.get_state() };
+                    let app_mode = current_app_state.app_state_store.get_app_mode();
+                    let current_route = current_app_state.router_store.get_current_route();
+
+                    // the command bar doesn't take pastes yet
+                    if app_mode != AppMode::Command && current_route == "/editor" {
+                        state_manager.update(AppStateActions::EditorActions(
+                            EditorContainerModelActions::Paste(text),
+                        ));
+                    }
+                }
             };
         }
 
@@ -146,6 +171,7 @@ impl UiManager {
         frame: &mut Frame,
         view: &mut Box<dyn View>,
         command_bar: &CommandBar,
+        popup_view: &PopupView,
         current_state: &AppState,
         model_manager: &ModelManager,
     ) {
@@ -172,6 +198,9 @@ impl UiManager {
         }
 
         view.render(frame, rect, &current_state);
+        if current_state.popup_store.get_is_open() {
+            popup_view.render(frame, rect, current_state);
+        }
         command_bar.render(frame, main_layout[1], current_state);
     }
 }
@@ -179,7 +208,8 @@ impl UiManager {
 fn setup_terminal() -> anyhow::Result<Terminal<CrosstermBackend<Stdout>>> {
     let mut stdout = io::stdout();
     enable_raw_mode()?;
-    execute!(stdout, EnterAlternateScreen)?;
+    // without it a paste arrives as one key event per char, and every newline is an enter
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
 
     Ok(Terminal::new(CrosstermBackend::new(stdout))?)
 }
@@ -191,7 +221,7 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow
 
 fn reset() -> Result<()> {
     disable_raw_mode()?;
-    crossterm::execute!(io::stderr(), LeaveAlternateScreen)?;
+    crossterm::execute!(io::stderr(), DisableBracketedPaste, LeaveAlternateScreen)?;
     Ok(())
 }
 
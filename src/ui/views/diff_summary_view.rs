@@ -0,0 +1,263 @@
+use crossterm::event::KeyCode::{self, Char};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::{
+    diff::{ChangeKind, DiffKind, DiffResult},
+    models::{
+        app_state::{AppState, AppStateActions},
+        diff_summary::DiffSummaryModelActions,
+        editor::editor_container_models::EditorContainerModelActions,
+    },
+    ui::command_bar::view::truncate,
+};
+
+use super::view::{View, ViewContext};
+
+const MAX_VALUE_LENGTH: usize = 40;
+
+// one line of the panel, either a structural change or a hunk of a text diff
+struct DiffSummaryEntry {
+    kind: ChangeKind,
+    label: String,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    left_line: Option<usize>,
+    right_line: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct DiffSummaryView {}
+
+impl DiffSummaryView {
+    pub fn new() -> Self {
+        DiffSummaryView {}
+    }
+
+    fn get_entries(&self, diff: &DiffResult) -> Vec<DiffSummaryEntry> {
+        match diff.kind {
+            DiffKind::Structural => diff
+                .changes
+                .iter()
+                .map(|change| DiffSummaryEntry {
+                    kind: change.kind,
+                    label: if change.path.is_empty() {
+                        "(root)".into()
+                    } else {
+                        change.path.clone()
+                    },
+                    old_value: change.old_value.clone(),
+                    new_value: change.new_value.clone(),
+                    left_line: change.left_lines.map(|lines| lines.0),
+                    right_line: change.right_lines.map(|lines| lines.0),
+                })
+                .collect(),
+            // text diffs don't have paths, so we list hunks in the unified diff notation
+            DiffKind::Text => diff
+                .hunks
+                .iter()
+                .map(|hunk| {
+                    let rows = &diff.rows[hunk.0..=hunk.1];
+                    let left_lines: Vec<usize> = rows.iter().filter_map(|row| row.left).collect();
+                    let right_lines: Vec<usize> = rows.iter().filter_map(|row| row.right).collect();
+
+                    let kind = match (left_lines.is_empty(), right_lines.is_empty()) {
+                        (true, _) => ChangeKind::Added,
+                        (_, true) => ChangeKind::Removed,
+                        _ => ChangeKind::Changed,
+                    };
+
+                    DiffSummaryEntry {
+                        kind,
+                        label: format!(
+                            "@@ -{} +{}",
+                            format_line_range(&left_lines),
+                            format_line_range(&right_lines)
+                        ),
+                        old_value: None,
+                        new_value: None,
+                        left_line: left_lines.first().copied(),
+                        right_line: right_lines.first().copied(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn select(&self, app_state: &AppState, offset: isize) -> Option<AppStateActions> {
+        let diff = app_state.editor_store.get_diff()?;
+        let entries_count = self.get_entries(diff).len();
+        if entries_count == 0 {
+            return None;
+        }
+
+        let selected_index = app_state.diff_summary_store.get_selected_index() as isize;
+        let new_index = (selected_index + offset).clamp(0, entries_count as isize - 1);
+        Some(AppStateActions::DiffSummaryActions(
+            DiffSummaryModelActions::Select(new_index as usize),
+        ))
+    }
+}
+
+impl View for DiffSummaryView {
+    fn get_has_been_initialized(&self, _app_state: &AppState) -> bool {
+        true
+    }
+
+    fn get_has_been_resized(&self, _app_state: &AppState) -> bool {
+        false
+    }
+
+    fn init(
+        &mut self,
+        _frame: &mut Frame,
+        _rect: Rect,
+        _app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        None
+    }
+
+    fn render(&self, frame: &mut Frame, rect: Rect, app_state: &AppState) {
+        let is_focused = app_state.diff_summary_store.get_is_focused();
+        let border_style = if is_focused {
+            Style::default().fg(Color::Blue)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        let Some(diff) = app_state.editor_store.get_diff() else {
+            let block = Block::new()
+                .title(" Changes ")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(border_style);
+            frame.render_widget(
+                Paragraph::new("No diff yet, run :diff first").block(block),
+                rect,
+            );
+            return;
+        };
+
+        let entries = self.get_entries(diff);
+        let title = match app_state.editor_store.get_diff_projection() {
+            Some(projection) => {
+                format!(" Changes in {} ({}) ", projection.expression, entries.len())
+            }
+            None => format!(" Changes ({}) ", entries.len()),
+        };
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(border_style);
+
+        let items: Vec<ListItem> = entries.iter().map(render_entry).collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        let mut list_state = ListState::default();
+        if !entries.is_empty() {
+            list_state.select(Some(
+                app_state
+                    .diff_summary_store
+                    .get_selected_index()
+                    .min(entries.len() - 1),
+            ));
+        }
+        frame.render_stateful_widget(list, rect, &mut list_state);
+    }
+
+    fn handle_event(
+        &mut self,
+        key_event: &crossterm::event::KeyEvent,
+        context: ViewContext,
+        app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        match (key_event.code, context.is_ctrl_pressed) {
+            (Char('k'), true) | (KeyCode::Esc, _) => Some(AppStateActions::DiffSummaryActions(
+                DiffSummaryModelActions::Unfocus,
+            )),
+            (Char('q'), _) => Some(AppStateActions::DiffSummaryActions(
+                DiffSummaryModelActions::Toggle,
+            )),
+            (Char('j'), _) | (KeyCode::Down, _) => self.select(app_state, 1),
+            (Char('k'), _) | (KeyCode::Up, _) => self.select(app_state, -1),
+            (KeyCode::Enter, _) => {
+                let diff = app_state.editor_store.get_diff()?;
+                let entries = self.get_entries(diff);
+                let selected_index = app_state.diff_summary_store.get_selected_index();
+                let entry = entries.get(selected_index.min(entries.len().checked_sub(1)?))?;
+
+                Some(AppStateActions::EditorActions(
+                    EditorContainerModelActions::JumpToLines(entry.left_line, entry.right_line),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_resize(
+        &mut self,
+        _frame: &mut Frame,
+        _rect: Rect,
+        _app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        None
+    }
+}
+
+fn render_entry<'a>(entry: &DiffSummaryEntry) -> ListItem<'a> {
+    let (marker, color) = match entry.kind {
+        ChangeKind::Added => ("+ ", Color::Green),
+        ChangeKind::Removed => ("- ", Color::Red),
+        ChangeKind::Changed | ChangeKind::TypeChanged => ("~ ", Color::Yellow),
+        ChangeKind::TypeOnly => ("≈ ", Color::Magenta),
+    };
+
+    let mut spans = vec![
+        Span::styled(marker, Style::default().fg(color)),
+        Span::styled(
+            entry.label.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw("  "),
+    ];
+
+    match (entry.old_value.clone(), entry.new_value.clone()) {
+        (Some(old_value), Some(new_value)) => {
+            spans.push(Span::styled(
+                truncate(&old_value, MAX_VALUE_LENGTH),
+                Style::default().fg(Color::Red),
+            ));
+            spans.push(Span::raw(" → "));
+            spans.push(Span::styled(
+                truncate(&new_value, MAX_VALUE_LENGTH),
+                Style::default().fg(Color::Green),
+            ));
+        }
+        (Some(old_value), None) => spans.push(Span::styled(
+            truncate(&old_value, MAX_VALUE_LENGTH),
+            Style::default().fg(Color::Red),
+        )),
+        (None, Some(new_value)) => spans.push(Span::styled(
+            truncate(&new_value, MAX_VALUE_LENGTH),
+            Style::default().fg(Color::Green),
+        )),
+        (None, None) => {}
+    }
+
+    ListItem::new(Line::from(spans))
+}
+
+fn format_line_range(lines: &[usize]) -> String {
+    match lines.first() {
+        Some(first_line) => format!("{},{}", first_line + 1, lines.len()),
+        None => "0,0".into(),
+    }
+}
@@ -10,31 +10,71 @@
 //
 // by default, there's only one editor.
 
+use crossterm::event::KeyCode;
 use ratatui::{
-    Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    text::{Line, Text},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
     widgets::Paragraph,
+    Frame,
 };
 
+use crate::diff::{ChangeKind, DiffKind, DiffResult, DiffSide};
+use crate::json::{
+    highlight::{HighlightKind, HighlightSpan},
+    value::JsonNode,
+};
 use crate::models::{
     app_model::{AppMode, AppModelActions},
     app_state::{AppState, AppStateActions},
+    command_bar::CommandBarModelActions,
+    diff_summary::DiffSummaryModelActions,
     editor::{
-        editor_container_models::{EditorContainerModelActions, EditorFocus},
+        editor_container_models::{
+            DiffJump, EditorContainerModelActions, EditorFocus, UNNAMED_REGISTER,
+        },
         editor_model::{EditorCursorDirection, EditorModel},
+        fold_model::FoldModel,
+        tree_model::{get_preview, TreeModel, TreeModelActions, TreeRow},
     },
 };
 
-use super::view::{View, ViewContext};
+use super::{
+    diff_summary_view::DiffSummaryView,
+    view::{View, ViewContext},
+};
+
+const DIFF_SUMMARY_HEIGHT: u16 = 10;
 
 #[derive(Default)]
-pub struct EditorView {}
+pub struct EditorView {
+    // some normal mode motions take two keys, like `]c`. The first one waits here
+    pending_key: Option<char>,
+    // picked with "a, the next yank, delete or put uses it
+    pending_register: Option<char>,
+    diff_summary_view: DiffSummaryView,
+}
 
 impl EditorView {
     pub fn new() -> Self {
-        EditorView {}
+        EditorView {
+            pending_key: None,
+            pending_register: None,
+            diff_summary_view: DiffSummaryView::new(),
+        }
+    }
+
+    // the editors get whatever the diff summary panel leaves them
+    fn split_layout(&self, layout: Rect, app_state: &AppState) -> (Rect, Option<Rect>) {
+        if !app_state.diff_summary_store.get_is_open() {
+            return (layout, None);
+        }
+
+        let panel_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(DIFF_SUMMARY_HEIGHT)])
+            .split(layout);
+        (panel_layout[0], Some(panel_layout[1]))
     }
 }
 
@@ -51,14 +91,21 @@ impl View for EditorView {
         &mut self,
         _frame: &mut Frame,
         rect: Rect,
-        _app_state: &AppState,
+        app_state: &AppState,
     ) -> Option<AppStateActions> {
+        let (editors_rect, _) = self.split_layout(rect, app_state);
         Some(AppStateActions::EditorActions(
-            EditorContainerModelActions::InitEditor(rect),
+            EditorContainerModelActions::InitEditor(editors_rect),
         ))
     }
 
     fn render(&self, frame: &mut Frame, layout: Rect, app_state: &AppState) {
+        let (layout, diff_summary_layout) = self.split_layout(layout, app_state);
+        if let Some(diff_summary_layout) = diff_summary_layout {
+            self.diff_summary_view
+                .render(frame, diff_summary_layout, app_state);
+        }
+
         let editors = app_state.editor_store.get_editors();
         let current_percentage = if editors.len() == 2 { 50 } else { 100 };
 
@@ -71,17 +118,43 @@ impl View for EditorView {
             .split(layout);
 
         for (index, editor) in editors.iter().enumerate() {
-            self.render_editor(frame, editors_container_layout[index], app_state, editor);
+            self.render_editor(
+                frame,
+                editors_container_layout[index],
+                app_state,
+                editor,
+                index,
+            );
         }
 
-        let side_rect_used =
-            editors_container_layout[app_state.editor_store.get_active_editor_index()];
+        let active_editor_index = app_state.editor_store.get_active_editor_index();
+        let side_rect_used = editors_container_layout[active_editor_index];
         let cursor_position = app_state.editor_store.get_active_cursor_position();
 
+        // with a diff active, filler lines can push the cursor further down
+        let cursor_row = app_state
+            .editor_store
+            .get_display_rows(active_editor_index)
+            .iter()
+            .position(|row| *row == Some(cursor_position.1))
+            .unwrap_or(0) as u16;
+
+        // the tree marks the selected node itself
+        if app_state.diff_summary_store.get_is_focused()
+            || app_state.popup_store.get_is_open()
+            || app_state
+                .editor_store
+                .get_active_editor()
+                .get_tree()
+                .is_some()
+        {
+            return;
+        }
+
         frame.set_cursor(
             // 4 is the line length, move that to a const
-            side_rect_used.x + 4 + cursor_position.0,
-            side_rect_used.y + cursor_position.1,
+            side_rect_used.x + 4 + cursor_position.0 as u16,
+            side_rect_used.y + cursor_row,
         )
     }
 
@@ -91,11 +164,35 @@ impl View for EditorView {
         context: ViewContext,
         app_state: &AppState,
     ) -> Option<AppStateActions> {
+        if app_state.diff_summary_store.get_is_focused() {
+            return self
+                .diff_summary_view
+                .handle_event(key_event, context, app_state);
+        }
+        // a half typed `za` or `"a` is dropped by anything that isn't a char, Esc included
+        if !matches!(key_event.code, KeyCode::Char(_)) {
+            self.pending_key = None;
+            self.pending_register = None;
+        }
+
         let current_app_mode = app_state.app_state_store.get_app_mode();
+        let is_tree_shown = app_state
+            .editor_store
+            .get_active_editor()
+            .get_tree()
+            .is_some();
         match (key_event.code, current_app_mode) {
+            (KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::Enter, _)
+                if is_tree_shown =>
+            {
+                self.handle_tree_input(key_event.code)
+            }
             (crossterm::event::KeyCode::Char(c), _) => {
                 self.handle_keyboard_input(&context, app_state, c)
             }
+            (crossterm::event::KeyCode::Esc, AppMode::Visual | AppMode::VisualLine) => Some(
+                AppStateActions::EditorActions(EditorContainerModelActions::ClearSelection),
+            ),
             (crossterm::event::KeyCode::Esc, _) => Some(AppStateActions::AppModelActions(
                 AppModelActions::ChangeMode(AppMode::Normal),
             )),
@@ -128,12 +225,13 @@ impl View for EditorView {
 
     fn handle_resize(
         &mut self,
-        frame: &mut Frame,
+        _frame: &mut Frame,
         rect: Rect,
         app_state: &AppState,
     ) -> Option<AppStateActions> {
+        let (editors_rect, _) = self.split_layout(rect, app_state);
         Some(AppStateActions::EditorActions(
-            EditorContainerModelActions::ResizeEditor(rect),
+            EditorContainerModelActions::ResizeEditor(editors_rect),
         ))
     }
 }
@@ -143,19 +241,34 @@ impl EditorView {
         &self,
         frame: &mut Frame,
         layout: Rect,
-        _app_state: &AppState,
+        app_state: &AppState,
         editor: &EditorModel,
+        editor_index: usize,
     ) {
-        let editor_visible_lines = editor.get_visible_lines();
-        let mut constraints = vec![Constraint::Max(1); editor.get_visible_lines().len() + 1];
-        constraints.push(Constraint::Max(1));
+        if let Some(tree) = editor.get_tree() {
+            self.render_tree(frame, layout, app_state, editor, tree, editor_index);
+            return;
+        }
+
+        let diff = app_state.editor_store.get_diff();
+        let side = DiffSide::from_editor_index(editor_index);
+        let display_rows = app_state.editor_store.get_display_rows(editor_index);
+        let is_selecting = editor_index == app_state.editor_store.get_active_editor_index()
+            && matches!(
+                app_state.app_state_store.get_app_mode(),
+                AppMode::Visual | AppMode::VisualLine
+            );
+        // one entry per row that has a line, in the same order
+        let lines: Vec<usize> = display_rows.iter().flatten().copied().collect();
+        let mut highlights = editor.get_highlights(&lines).into_iter();
+        let constraints = vec![Constraint::Length(1); display_rows.len()];
 
         let editor_lines_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(constraints)
             .split(layout);
 
-        for (index, line_data) in editor_visible_lines.iter().enumerate() {
+        for (index, row) in display_rows.iter().enumerate() {
             let line_layout = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([
@@ -164,24 +277,163 @@ impl EditorView {
                     Constraint::Percentage(100),
                 ])
                 .split(editor_lines_layout[index]);
-            let number_widget = Text::from(Line::from(line_data.0.to_string()));
+            let line_highlights = row.and_then(|_| highlights.next());
+
+            let Some((line, text)) = row.and_then(|line| Some((line, editor.get_line(line)?)))
+            else {
+                // the other side has lines that this one doesn't, pad it so they stay side by side
+                let filler_paragraph = Paragraph::new("╱".repeat(line_layout[2].width as usize))
+                    .style(Style::default().fg(Color::DarkGray));
+                frame.render_widget(
+                    Paragraph::default().style(Style::default().bg(Color::DarkGray)),
+                    line_layout[0],
+                );
+                frame.render_widget(filler_paragraph, line_layout[2]);
+                continue;
+            };
+
+            let line_status = diff.and_then(|diff| diff.get_line_status(side, line));
+            let (marker, line_style) = get_change_marker(line_status);
+
+            // a parse error is more urgent than whatever the diff has to say about the line
+            let is_error_line = editor
+                .get_parse_error()
+                .is_some_and(|error| error.line == line);
+            let (marker, marker_style) = if is_error_line {
+                (
+                    "✗",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                (marker, line_style)
+            };
+
+            let number_widget = Text::from(Line::from((line + 1).to_string()));
             let number_paragraph = Paragraph::new(number_widget)
                 .alignment(Alignment::Center)
                 .style(Style::default().bg(Color::DarkGray));
-            let text_widget = Text::from(Line::from(line_data.1.to_string()));
-            let text_paragraph = Paragraph::new(text_widget).alignment(Alignment::Left);
+            let marker_paragraph = Paragraph::new(marker).style(marker_style);
+            // changed lines keep their diff colour, it matters more than the syntax
+            let mut text_line = match (line_status, line_highlights) {
+                (None, Some(line_highlights)) => highlight_line(&text, &line_highlights),
+                _ => Line::from(text),
+            };
+            for (start_column, end_column) in editor.get_search_matches(line) {
+                text_line = style_columns(
+                    text_line,
+                    start_column,
+                    end_column,
+                    Style::default().fg(Color::Black).bg(Color::Yellow),
+                );
+            }
+            if let Some((start_column, end_column)) =
+                editor.get_selected_columns(line).filter(|_| is_selecting)
+            {
+                text_line = style_columns(
+                    text_line,
+                    start_column,
+                    end_column,
+                    Style::default().add_modifier(Modifier::REVERSED),
+                );
+            }
+            if let Some(fold) = editor.get_fold(line) {
+                text_line = fold_line(text_line, fold);
+            }
+            let text_widget = Text::from(text_line);
+            let text_paragraph = Paragraph::new(text_widget)
+                .alignment(Alignment::Left)
+                .style(line_style);
 
             frame.render_widget(number_paragraph, line_layout[0]);
+            frame.render_widget(marker_paragraph, line_layout[1]);
             frame.render_widget(text_paragraph, line_layout[2]);
         }
     }
 
+    // a marker column like the text has, then the nodes indented by depth
+    fn render_tree(
+        &self,
+        frame: &mut Frame,
+        layout: Rect,
+        app_state: &AppState,
+        editor: &EditorModel,
+        tree: &TreeModel,
+        editor_index: usize,
+    ) {
+        let Some(document) = editor.get_document() else {
+            let message = editor
+                .get_parse_error()
+                .map_or(String::new(), |error| format!(" ✗ {}", error));
+            frame.render_widget(
+                Paragraph::new(message).style(Style::default().fg(Color::Red)),
+                layout,
+            );
+            return;
+        };
+
+        let diff = app_state.editor_store.get_diff();
+        let side = DiffSide::from_editor_index(editor_index);
+        let is_active = editor_index == app_state.editor_store.get_active_editor_index();
+        let rows = tree.get_rows();
+        let selected_index = tree.get_selected_index();
+        let scroll_offset = tree.get_scroll_offset().min(selected_index);
+
+        let lines: Vec<Line> = rows
+            .iter()
+            .enumerate()
+            .skip(scroll_offset)
+            .take(layout.height as usize)
+            .map(|(index, row)| {
+                let line_status = diff.and_then(|diff| get_row_status(diff, side, row));
+                let mut line = tree_line(row, line_status, document);
+                if index == selected_index {
+                    let selected_style = if is_active {
+                        Style::default().add_modifier(Modifier::REVERSED)
+                    } else {
+                        Style::default().bg(Color::DarkGray)
+                    };
+                    line = line.patch_style(selected_style);
+                }
+                line
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), layout);
+    }
+
+    // j/k move, l opens, h closes or goes up, Enter and za toggle, zR and zM do everything
+    fn handle_tree_input(&mut self, code: KeyCode) -> Option<AppStateActions> {
+        let action = match (self.pending_key.take(), code) {
+            (Some('z'), KeyCode::Char('a')) => TreeModelActions::Toggle,
+            (Some('z'), KeyCode::Char('R')) => TreeModelActions::ExpandAll,
+            (Some('z'), KeyCode::Char('M')) => TreeModelActions::CollapseAll,
+            (Some('g'), KeyCode::Char('g')) => TreeModelActions::MoveSelection(isize::MIN),
+            (Some(_), _) => return None,
+            (None, KeyCode::Char('j') | KeyCode::Down) => TreeModelActions::MoveSelection(1),
+            (None, KeyCode::Char('k') | KeyCode::Up) => TreeModelActions::MoveSelection(-1),
+            (None, KeyCode::Char('l') | KeyCode::Right) => TreeModelActions::Expand,
+            (None, KeyCode::Char('h') | KeyCode::Left) => TreeModelActions::Collapse,
+            (None, KeyCode::Char(' ') | KeyCode::Enter) => TreeModelActions::Toggle,
+            (None, KeyCode::Char('G')) => TreeModelActions::MoveSelection(isize::MAX),
+            (None, KeyCode::Char(c @ ('z' | 'g'))) => {
+                self.pending_key = Some(c);
+                return None;
+            }
+            _ => return None,
+        };
+        Some(AppStateActions::EditorActions(
+            EditorContainerModelActions::TreeActions(action),
+        ))
+    }
+
     fn handle_keyboard_input(
         &mut self,
         context: &ViewContext,
         app_state: &AppState,
         c: char,
     ) -> Option<AppStateActions> {
+        // whatever key comes after "a uses the register or drops it
+        let pending_register = self.pending_register.take();
         let char = c
             .to_lowercase()
             .collect::<Vec<_>>()
@@ -199,10 +451,91 @@ impl EditorView {
                     EditorContainerModelActions::ChangeFocus(EditorFocus::Next),
                 ))
             }
+            ('j', true) if app_state.diff_summary_store.get_is_open() => {
+                return Some(AppStateActions::DiffSummaryActions(
+                    DiffSummaryModelActions::Focus,
+                ))
+            }
             (_, _) => {}
         }
 
+        // there's no text to edit or select while the pane shows a tree
+        if app_state
+            .editor_store
+            .get_active_editor()
+            .get_tree()
+            .is_some()
+        {
+            return self.handle_tree_input(KeyCode::Char(c));
+        }
+
+        let app_mode = app_state.app_state_store.get_app_mode();
+        if matches!(app_mode, AppMode::Visual | AppMode::VisualLine) {
+            return self.handle_visual_input(app_mode, c, pending_register);
+        }
+
         if app_state.app_state_store.get_app_mode() == AppMode::Normal {
+            if let Some(pending_key) = self.pending_key.take() {
+                // fold commands care about case, zM and zm aren't the same thing in vim
+                return match (pending_key, c) {
+                    ('z', 'a') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::ToggleFold,
+                    )),
+                    ('z', 'M') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::FoldAll,
+                    )),
+                    ('z', 'R') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::UnfoldAll,
+                    )),
+                    ('g', '-') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::EarlierState,
+                    )),
+                    ('g', '+') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::LaterState,
+                    )),
+                    (']', 'c') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::JumpToDiff(DiffJump::Next),
+                    )),
+                    ('[', 'c') => Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::JumpToDiff(DiffJump::Prev),
+                    )),
+                    ('"', register) if is_register_name(register) => {
+                        self.pending_register = Some(register);
+                        None
+                    }
+                    _ => None,
+                };
+            }
+
+            if matches!(c, ']' | '[' | 'z' | 'g' | '"') {
+                self.pending_key = Some(c);
+                return None;
+            }
+
+            match c {
+                '/' | '?' => {
+                    return Some(AppStateActions::CommandBarActions(
+                        CommandBarModelActions::Start(c),
+                    ))
+                }
+                'n' | 'N' => {
+                    return Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::FindNext(c == 'N'),
+                    ))
+                }
+                'v' | 'V' => {
+                    return Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::StartSelection(c == 'V'),
+                    ))
+                }
+                'p' | 'P' => {
+                    return Some(AppStateActions::EditorActions(
+                        EditorContainerModelActions::Put(pending_register, c == 'P'),
+                    ))
+                }
+                _ => {}
+            }
+
             if char == 'i' {
                 return Some(AppStateActions::AppModelActions(
                     AppModelActions::ChangeMode(AppMode::Editing),
@@ -228,4 +561,246 @@ impl EditorView {
         }
         None
     }
+
+    fn handle_visual_input(
+        &mut self,
+        app_mode: AppMode,
+        c: char,
+        pending_register: Option<char>,
+    ) -> Option<AppStateActions> {
+        if let Some(pending_key) = self.pending_key.take() {
+            if pending_key == '"' && is_register_name(c) {
+                self.pending_register = Some(c);
+            }
+            return None;
+        }
+
+        let action = match (c, app_mode) {
+            ('"', _) => {
+                self.pending_key = Some(c);
+                return None;
+            }
+            // pressing v again in v leaves, pressing the other one switches to it
+            ('v', AppMode::Visual) | ('V', AppMode::VisualLine) => {
+                EditorContainerModelActions::ClearSelection
+            }
+            ('v' | 'V', _) => EditorContainerModelActions::StartSelection(c == 'V'),
+            ('y', _) => EditorContainerModelActions::Yank(pending_register),
+            ('d' | 'x', _) => EditorContainerModelActions::DeleteSelection(pending_register),
+            _ => return None,
+        };
+        Some(AppStateActions::EditorActions(action))
+    }
+}
+
+fn is_register_name(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == UNNAMED_REGISTER
+}
+
+// patches the style of some columns of the line, for selections and search matches.
+// A selection taking the line break shows as an extra cell
+fn style_columns<'a>(
+    line: Line<'a>,
+    start_column: usize,
+    end_column: usize,
+    selected_style: Style,
+) -> Line<'a> {
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut column = 0;
+
+    for span in line.spans {
+        let chars: Vec<char> = span.content.chars().collect();
+        let span_start = column;
+        column += chars.len();
+
+        let selected_start = start_column.clamp(span_start, column) - span_start;
+        let selected_end = end_column.clamp(span_start, column) - span_start;
+        if selected_start == selected_end {
+            spans.push(span);
+            continue;
+        }
+
+        let parts = [
+            (0, selected_start, span.style),
+            (
+                selected_start,
+                selected_end,
+                span.style.patch(selected_style),
+            ),
+            (selected_end, chars.len(), span.style),
+        ];
+        for (part_start, part_end, style) in parts {
+            if part_start < part_end {
+                spans.push(Span::styled(
+                    chars[part_start..part_end].iter().collect::<String>(),
+                    style,
+                ));
+            }
+        }
+    }
+
+    if end_column > column {
+        spans.push(Span::styled(
+            " ".repeat(end_column - column.max(start_column)),
+            selected_style,
+        ));
+    }
+    Line::from(spans)
+}
+
+fn highlight_line<'a>(text: &str, line_highlights: &[HighlightSpan]) -> Line<'a> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::with_capacity(line_highlights.len() * 2);
+    let mut position = 0;
+
+    for line_highlight in line_highlights {
+        if line_highlight.start_column > position {
+            spans.push(Span::raw(
+                chars[position..line_highlight.start_column]
+                    .iter()
+                    .collect::<String>(),
+            ));
+        }
+        spans.push(Span::styled(
+            chars[line_highlight.start_column..line_highlight.end_column]
+                .iter()
+                .collect::<String>(),
+            get_highlight_style(line_highlight.kind),
+        ));
+        position = line_highlight.end_column;
+    }
+
+    if position < chars.len() {
+        spans.push(Span::raw(chars[position..].iter().collect::<String>()));
+    }
+
+    Line::from(spans)
+}
+
+// cuts the line right after the opening bracket and puts the summary there
+fn fold_line<'a>(line: Line<'a>, fold: &FoldModel) -> Line<'a> {
+    let mut spans = Vec::new();
+    let mut remaining = fold.prefix_length;
+
+    for span in line.spans {
+        if remaining == 0 {
+            break;
+        }
+        let content: String = span.content.chars().take(remaining).collect();
+        remaining -= content.chars().count();
+        spans.push(Span::styled(content, span.style));
+    }
+
+    spans.push(Span::styled(
+        fold.summary.clone(),
+        Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC),
+    ));
+    spans.push(Span::styled(
+        fold.suffix.clone(),
+        Style::default().fg(Color::Gray),
+    ));
+    Line::from(spans)
+}
+
+fn get_change_marker(line_status: Option<ChangeKind>) -> (&'static str, Style) {
+    match line_status {
+        Some(ChangeKind::Added) => ("+", Style::default().fg(Color::Green)),
+        Some(ChangeKind::Removed) => ("-", Style::default().fg(Color::Red)),
+        Some(ChangeKind::Changed) | Some(ChangeKind::TypeChanged) => {
+            ("~", Style::default().fg(Color::Yellow))
+        }
+        Some(ChangeKind::TypeOnly) => ("≈", Style::default().fg(Color::Magenta)),
+        None => (" ", Style::default()),
+    }
+}
+
+// collapsed rows say whether anything inside them changed. Structural diffs know the
+// exact node, text diffs only know lines, so siblings sharing a line all get marked
+fn get_row_status(diff: &DiffResult, side: DiffSide, row: &TreeRow) -> Option<ChangeKind> {
+    if diff.kind == DiffKind::Text {
+        let last_line = if row.is_expanded {
+            row.start_line
+        } else {
+            row.end_line
+        };
+        return (row.start_line..=last_line).find_map(|line| diff.get_line_status(side, line));
+    }
+
+    diff.get_path_status(&row.pointer, !row.is_expanded)
+}
+
+// `▸ users [2 items]`, or `id: 1` for anything that isn't a container
+fn tree_line<'a>(row: &TreeRow, line_status: Option<ChangeKind>, document: &JsonNode) -> Line<'a> {
+    let (marker, marker_style) = get_change_marker(line_status);
+    let arrow = match (row.child_count, row.is_expanded) {
+        (Some(count), false) if count > 0 => "▸ ",
+        (Some(_), true) => "▾ ",
+        _ => "  ",
+    };
+    let label_style = if row.is_key {
+        get_highlight_style(HighlightKind::Key)
+    } else {
+        get_highlight_style(HighlightKind::Punctuation)
+    };
+
+    let mut spans = vec![
+        Span::styled(format!("{} ", marker), marker_style),
+        Span::raw("  ".repeat(row.depth)),
+        Span::styled(arrow, Style::default().fg(Color::Gray)),
+        Span::styled(row.label.clone(), label_style),
+    ];
+    match row.child_count {
+        Some(count) => {
+            let (open, close, noun) = match (row.is_object, count) {
+                (true, 1) => ("{", "}", "key"),
+                (true, _) => ("{", "}", "keys"),
+                (false, 1) => ("[", "]", "item"),
+                (false, _) => ("[", "]", "items"),
+            };
+            spans.push(Span::styled(
+                format!(" {}{} {}{}", open, count, noun, close),
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            ));
+        }
+        None => {
+            let kind = match row.type_name {
+                "string" => HighlightKind::String,
+                "number" => HighlightKind::Number,
+                "boolean" => HighlightKind::Boolean,
+                _ => HighlightKind::Null,
+            };
+            spans.push(Span::styled(
+                ": ",
+                get_highlight_style(HighlightKind::Punctuation),
+            ));
+            spans.push(Span::styled(
+                get_preview(document, row),
+                get_highlight_style(kind),
+            ));
+        }
+    }
+
+    // the change colour matters more than the syntax, same as in the text
+    let line = Line::from(spans);
+    match line_status {
+        Some(_) => line.patch_style(marker_style),
+        None => line,
+    }
+}
+
+fn get_highlight_style(kind: HighlightKind) -> Style {
+    match kind {
+        HighlightKind::Key => Style::default().fg(Color::Cyan),
+        HighlightKind::String => Style::default().fg(Color::Green),
+        HighlightKind::Number => Style::default().fg(Color::LightYellow),
+        HighlightKind::Boolean | HighlightKind::Null => Style::default().fg(Color::LightMagenta),
+        HighlightKind::Punctuation => Style::default().fg(Color::Gray),
+        HighlightKind::Invalid => Style::default()
+            .fg(Color::Red)
+            .add_modifier(Modifier::UNDERLINED),
+    }
 }
@@ -1,3 +1,5 @@
+pub mod diff_summary_view;
 pub mod editor_view;
+pub mod popup_view;
 pub mod view;
 pub mod welcome_view;
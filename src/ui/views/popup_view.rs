@@ -0,0 +1,218 @@
+use crossterm::event::KeyCode::{self, Char};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::{
+    models::{
+        app_state::{AppState, AppStateActions},
+        popup::{PasteMode, Popup, PopupModelActions, QUERY_RESULT_PREVIEW_LENGTH},
+    },
+    ui::command_bar::view::truncate,
+};
+
+use super::view::{View, ViewContext};
+
+const POPUP_WIDTH: u16 = 50;
+const QUERY_POPUP_WIDTH: u16 = 90;
+
+#[derive(Default)]
+pub struct PopupView {}
+
+impl PopupView {
+    pub fn new() -> Self {
+        PopupView {}
+    }
+
+    fn get_options(&self, popup: &Popup, app_state: &AppState) -> Vec<String> {
+        let editors_count = app_state.editor_store.get_editors().len();
+        match popup {
+            Popup::PasteTarget { .. } => {
+                let editors = app_state.editor_store.get_editors();
+                editors
+                    .iter()
+                    .enumerate()
+                    .map(|(index, editor)| {
+                        let side = get_side_name(index, editors_count);
+                        let preview = editor.get_line(0).unwrap_or_default();
+                        format!(
+                            "{} {}  {} lines  {}",
+                            index + 1,
+                            side,
+                            editor.get_line_count(),
+                            truncate(preview.trim(), QUERY_RESULT_PREVIEW_LENGTH)
+                        )
+                    })
+                    .collect()
+            }
+            // which pane only matters when there's more than one
+            Popup::QueryResults { results, .. } => results
+                .iter()
+                .map(|result| match editors_count {
+                    1 => format!("{}  {}", result.path, result.value),
+                    _ => format!(
+                        "{:5} {}  {}",
+                        get_side_name(result.editor_index, editors_count),
+                        result.path,
+                        result.value
+                    ),
+                })
+                .collect(),
+        }
+    }
+
+    fn select(&self, app_state: &AppState, offset: isize) -> Option<AppStateActions> {
+        let popup = app_state.popup_store.get_popup()?;
+        let options_count = self.get_options(popup, app_state).len() as isize;
+        let selected_index = app_state.popup_store.get_selected_index() as isize;
+        let new_index = (selected_index + offset).clamp(0, (options_count - 1).max(0));
+        Some(AppStateActions::PopupActions(PopupModelActions::Select(
+            new_index as usize,
+        )))
+    }
+}
+
+impl View for PopupView {
+    fn get_has_been_initialized(&self, _app_state: &AppState) -> bool {
+        true
+    }
+
+    fn get_has_been_resized(&self, _app_state: &AppState) -> bool {
+        false
+    }
+
+    fn init(
+        &mut self,
+        _frame: &mut Frame,
+        _rect: Rect,
+        _app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        None
+    }
+
+    // `rect` is the area the popup sits on top of, it takes the middle of it
+    fn render(&self, frame: &mut Frame, rect: Rect, app_state: &AppState) {
+        let Some(popup) = app_state.popup_store.get_popup() else {
+            return;
+        };
+
+        let options = self.get_options(popup, app_state);
+        let (title, footer, width) = match popup {
+            Popup::PasteTarget { text, mode } => {
+                let mode_text = match mode {
+                    PasteMode::Insert => "insert at cursor",
+                    PasteMode::Replace => "replace contents",
+                };
+                (
+                    format!(" Paste {} lines into ", text.lines().count().max(1)),
+                    Line::from(vec![
+                        Span::raw(" Tab: "),
+                        Span::styled(mode_text, Style::default().add_modifier(Modifier::BOLD)),
+                        Span::styled("  Enter  Esc", Style::default().fg(Color::DarkGray)),
+                    ]),
+                    POPUP_WIDTH,
+                )
+            }
+            Popup::QueryResults {
+                expression,
+                results,
+            } => (
+                format!(" {} ({}) ", expression, results.len()),
+                Line::from(Span::styled(
+                    " Enter: jump to the node  Esc",
+                    Style::default().fg(Color::DarkGray),
+                )),
+                QUERY_POPUP_WIDTH,
+            ),
+        };
+
+        // long lists get cut to the screen, the list scrolls along with the selection
+        let height = options.len().saturating_add(3).min(rect.height as usize) as u16;
+        let popup_rect = center_rect(rect, width, height);
+        frame.render_widget(Clear, popup_rect);
+
+        let block = Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Blue));
+        let inner_rect = block.inner(popup_rect);
+        frame.render_widget(block, popup_rect);
+
+        let popup_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner_rect);
+
+        let items: Vec<ListItem> = options.into_iter().map(ListItem::new).collect();
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut list_state = ListState::default();
+        list_state.select(Some(app_state.popup_store.get_selected_index()));
+        frame.render_stateful_widget(list, popup_layout[0], &mut list_state);
+        frame.render_widget(Paragraph::new(footer), popup_layout[1]);
+    }
+
+    fn handle_event(
+        &mut self,
+        key_event: &crossterm::event::KeyEvent,
+        _context: ViewContext,
+        app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        match key_event.code {
+            KeyCode::Esc | Char('q') => {
+                Some(AppStateActions::PopupActions(PopupModelActions::Close))
+            }
+            KeyCode::Enter => Some(AppStateActions::PopupActions(PopupModelActions::Confirm)),
+            KeyCode::Tab => Some(AppStateActions::PopupActions(
+                PopupModelActions::TogglePasteMode,
+            )),
+            Char('j') | KeyCode::Down => self.select(app_state, 1),
+            Char('k') | KeyCode::Up => self.select(app_state, -1),
+            // options are numbered from 1, same as on screen
+            Char(c) if c.is_ascii_digit() => {
+                let popup = app_state.popup_store.get_popup()?;
+                let index = c.to_digit(10)?.checked_sub(1)? as usize;
+                if index >= self.get_options(popup, app_state).len() {
+                    return None;
+                }
+                Some(AppStateActions::PopupActions(PopupModelActions::Select(
+                    index,
+                )))
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_resize(
+        &mut self,
+        _frame: &mut Frame,
+        _rect: Rect,
+        _app_state: &AppState,
+    ) -> Option<AppStateActions> {
+        None
+    }
+}
+
+fn get_side_name(editor_index: usize, editors_count: usize) -> &'static str {
+    match (editor_index, editors_count) {
+        (_, 1) => "Editor",
+        (0, _) => "Left",
+        _ => "Right",
+    }
+}
+
+fn center_rect(rect: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(rect.width);
+    let height = height.min(rect.height);
+    Rect::new(
+        rect.x + (rect.width - width) / 2,
+        rect.y + (rect.height - height) / 2,
+        width,
+        height,
+    )
+}
@@ -31,7 +31,7 @@ pub trait View {
 
     fn init(
         &mut self,
-        rame: &mut Frame,
+        frame: &mut Frame,
         rect: Rect,
         app_state: &AppState,
     ) -> Option<AppStateActions>;